@@ -1,6 +1,20 @@
-use mongodb::{bson::doc, Client, Collection, Database};
-use crate::models::tarea::Tarea;
+use mongodb::{
+    bson::doc,
+    error::{ErrorKind, WriteFailure},
+    options::IndexOptions,
+    Client, Collection, Database, IndexModel,
+};
+use std::time::Duration;
+use crate::models::exportacion::Exportacion;
+use crate::models::intento_login::IntentoLogin;
+use crate::models::oidc::EstadoOidc;
+use crate::models::refresh_token::RefreshToken;
+use crate::models::revocacion::Revocacion;
+use crate::models::sesion::Sesion;
+use crate::models::tarea::{Prioridad, Tarea};
+use crate::models::token_personal::TokenPersonal;
 use crate::models::usuario::Usuario;
+use crate::utils::entorno::variable;
 
 pub type DbState = Database;
 
@@ -26,3 +40,285 @@ pub fn obtener_coleccion_tareas(db: &Database) -> Collection<Tarea> {
 pub fn obtener_coleccion_usuarios(db: &Database) -> Collection<Usuario> {
     db.collection::<Usuario>("usuarios")
 }
+
+pub fn obtener_coleccion_refresh_tokens(db: &Database) -> Collection<RefreshToken> {
+    db.collection::<RefreshToken>("refresh_tokens")
+}
+
+pub fn obtener_coleccion_revocaciones(db: &Database) -> Collection<Revocacion> {
+    db.collection::<Revocacion>("tokens_revocados")
+}
+
+pub fn obtener_coleccion_sesiones(db: &Database) -> Collection<Sesion> {
+    db.collection::<Sesion>("sesiones")
+}
+
+pub fn obtener_coleccion_intentos_login(db: &Database) -> Collection<IntentoLogin> {
+    db.collection::<IntentoLogin>("intentos_login")
+}
+
+pub fn obtener_coleccion_estados_oidc(db: &Database) -> Collection<EstadoOidc> {
+    db.collection::<EstadoOidc>("oidc_estados")
+}
+
+pub fn obtener_coleccion_tokens_personales(db: &Database) -> Collection<TokenPersonal> {
+    db.collection::<TokenPersonal>("tokens_personales")
+}
+
+pub fn obtener_coleccion_exportaciones(db: &Database) -> Collection<Exportacion> {
+    db.collection::<Exportacion>("exportaciones")
+}
+
+// Índice TTL: MongoDB elimina los documentos cuando vence `expires_at`
+fn indice_ttl(campo: &str) -> IndexModel {
+    IndexModel::builder()
+        .keys(doc! { campo: 1 })
+        .options(
+            IndexOptions::builder()
+                .expire_after(Duration::from_secs(0))
+                .build(),
+        )
+        .build()
+}
+
+// Escritura rechazada por un índice único (código 11000)
+pub fn es_clave_duplicada(error: &mongodb::error::Error) -> bool {
+    match error.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(e)) => e.code == 11000,
+        ErrorKind::Command(e) => e.code == 11000,
+        _ => false,
+    }
+}
+
+// Crea los índices necesarios al arrancar (operación idempotente)
+pub async fn crear_indices(db: &Database) -> Result<(), mongodb::error::Error> {
+    let refresh_tokens = obtener_coleccion_refresh_tokens(db);
+    refresh_tokens
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "token_hash": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            None,
+        )
+        .await?;
+    refresh_tokens
+        .create_index(IndexModel::builder().keys(doc! { "familia": 1 }).build(), None)
+        .await?;
+    refresh_tokens
+        .create_index(IndexModel::builder().keys(doc! { "usuario_id": 1 }).build(), None)
+        .await?;
+    refresh_tokens.create_index(indice_ttl("expires_at"), None).await?;
+
+    let revocaciones = obtener_coleccion_revocaciones(db);
+    revocaciones
+        .create_index(IndexModel::builder().keys(doc! { "usuario_id": 1 }).build(), None)
+        .await?;
+    revocaciones.create_index(indice_ttl("expires_at"), None).await?;
+
+    let sesiones = obtener_coleccion_sesiones(db);
+    sesiones
+        .create_index(IndexModel::builder().keys(doc! { "usuario_id": 1 }).build(), None)
+        .await?;
+    sesiones
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "sid": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            None,
+        )
+        .await?;
+    sesiones.create_index(indice_ttl("expires_at"), None).await?;
+
+    let intentos_login = obtener_coleccion_intentos_login(db);
+    intentos_login
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "clave": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            None,
+        )
+        .await?;
+    intentos_login.create_index(indice_ttl("expires_at"), None).await?;
+
+    let estados_oidc = obtener_coleccion_estados_oidc(db);
+    estados_oidc
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "state": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            None,
+        )
+        .await?;
+    estados_oidc.create_index(indice_ttl("expires_at"), None).await?;
+
+    let tokens_personales = obtener_coleccion_tokens_personales(db);
+    tokens_personales
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "token_hash": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            None,
+        )
+        .await?;
+    tokens_personales
+        .create_index(IndexModel::builder().keys(doc! { "usuario_id": 1 }).build(), None)
+        .await?;
+    tokens_personales.create_index(indice_ttl("expires_at"), None).await?;
+
+    let exportaciones = obtener_coleccion_exportaciones(db);
+    exportaciones
+        .create_index(IndexModel::builder().keys(doc! { "usuario_id": 1 }).build(), None)
+        .await?;
+    // Como mucho una exportación pendiente por usuario, también entre peticiones simultáneas
+    exportaciones
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "usuario_id": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .name("usuario_pendiente".to_string())
+                        .unique(true)
+                        .partial_filter_expression(doc! { "estado": "pendiente" })
+                        .build(),
+                )
+                .build(),
+            None,
+        )
+        .await?;
+
+    // Listado por usuario ordenado por fecha; _id desempata en la paginación por cursor
+    obtener_coleccion_tareas(db)
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "usuario_id": 1, "fecha_creacion": -1, "_id": -1 })
+                .build(),
+            None,
+        )
+        .await?;
+
+    // Vistas y orden por fecha de vencimiento
+    obtener_coleccion_tareas(db)
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "usuario_id": 1, "vencimiento.fecha": 1 })
+                .build(),
+            None,
+        )
+        .await?;
+    obtener_coleccion_tareas(db)
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "usuario_id": 1, "vencimiento.limite": 1 })
+                .build(),
+            None,
+        )
+        .await?;
+
+    obtener_coleccion_tareas(db)
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "usuario_id": 1, "prioridad": 1 })
+                .build(),
+            None,
+        )
+        .await?;
+
+    // Búsqueda de texto: cada tarea se indexa con las raíces y palabras vacías de su
+    // `idioma` (español si no lo tiene). La versión 3 del índice ya ignora mayúsculas y
+    // tildes
+    obtener_coleccion_tareas(db)
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "descripcion": "text" })
+                .options(
+                    IndexOptions::builder()
+                        .name("descripcion_texto_idioma".to_string())
+                        .default_language("spanish".to_string())
+                        .language_override("idioma".to_string())
+                        .build(),
+                )
+                .build(),
+            None,
+        )
+        .await?;
+
+    // Un email por cuenta: el registro y el login OIDC comprueban antes de insertar,
+    // pero solo el índice evita duplicados entre peticiones simultáneas
+    let usuarios = obtener_coleccion_usuarios(db);
+    usuarios
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "email": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            None,
+        )
+        .await?;
+    usuarios
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "identidades.proveedor": 1, "identidades.sub": 1 })
+                .build(),
+            None,
+        )
+        .await?;
+
+    Ok(())
+}
+
+// Pone al día las tareas guardadas por versiones anteriores:
+// - fechas de creación como texto pasan a fecha BSON, para que los filtros por rango y
+//   el orden funcionen sobre todas las tareas
+// - las tareas sin prioridad reciben la prioridad por defecto
+pub async fn migrar_tareas(db: &Database) -> Result<(), mongodb::error::Error> {
+    let tareas = db.collection::<mongodb::bson::Document>("tareas");
+
+    let fechas = tareas
+        .update_many(
+            doc! { "fecha_creacion": { "$type": "string" } },
+            vec![doc! { "$set": { "fecha_creacion": { "$toDate": "$fecha_creacion" } } }],
+            None,
+        )
+        .await?;
+    if fechas.modified_count > 0 {
+        println!("✓ Fechas migradas en {} tareas", fechas.modified_count);
+    }
+
+    let prioridades = tareas
+        .update_many(
+            doc! { "prioridad": { "$exists": false } },
+            doc! { "$set": { "prioridad": mongodb::bson::to_bson(&Prioridad::default()).unwrap() } },
+            None,
+        )
+        .await?;
+    if prioridades.modified_count > 0 {
+        println!("✓ Prioridad por defecto asignada a {} tareas", prioridades.modified_count);
+    }
+    Ok(())
+}
+
+// Concede el rol admin a la cuenta indicada en ADMIN_EMAIL (si existe)
+pub async fn promover_admin_inicial(db: &Database) -> Result<(), mongodb::error::Error> {
+    let email = match variable("ADMIN_EMAIL") {
+        Some(email) => email.to_lowercase(),
+        None => return Ok(()),
+    };
+
+    let resultado = obtener_coleccion_usuarios(db)
+        .update_one(
+            doc! { "email": &email },
+            doc! { "$addToSet": { "roles": { "$each": ["user", "admin"] } } },
+            None,
+        )
+        .await?;
+
+    if resultado.matched_count == 0 {
+        println!("⚠ ADMIN_EMAIL '{}' no corresponde a ningún usuario", email);
+    }
+    Ok(())
+}
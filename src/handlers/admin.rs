@@ -0,0 +1,127 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use bson::oid::ObjectId;
+use futures::stream::TryStreamExt;
+use mongodb::{bson::doc, options::FindOptions};
+
+use crate::{
+    db::mongo::{obtener_coleccion_tareas, obtener_coleccion_usuarios, DbState},
+    middleware::auth::Admin,
+    models::{
+        tarea::Tarea,
+        usuario::{Usuario, UsuarioAdmin},
+    },
+    utils::sesiones::cerrar_todas,
+};
+
+// GET /admin/usuarios - Listar todos los usuarios
+pub async fn listar_usuarios(
+    State(db): State<DbState>,
+    _admin: Admin,
+) -> Result<impl IntoResponse, StatusCode> {
+    let opciones = FindOptions::builder().sort(doc! { "created_at": 1 }).build();
+
+    match obtener_coleccion_usuarios(&db).find(None, opciones).await {
+        Ok(cursor) => {
+            let usuarios: Vec<Usuario> = cursor
+                .try_collect()
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            let vista: Vec<UsuarioAdmin> = usuarios.iter().map(Usuario::to_admin).collect();
+            Ok(Json(vista))
+        }
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+// Cambia el estado de la cuenta; al deshabilitarla se cierran todas sus sesiones
+async fn cambiar_estado_usuario(
+    db: &DbState,
+    admin: &Admin,
+    id: &str,
+    deshabilitado: bool,
+) -> Result<Json<UsuarioAdmin>, StatusCode> {
+    let object_id = ObjectId::parse_str(id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    // Un administrador no puede deshabilitar su propia cuenta
+    if deshabilitado && admin.0.sub == object_id.to_hex() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let coleccion = obtener_coleccion_usuarios(db);
+    match coleccion
+        .update_one(
+            doc! { "_id": object_id },
+            doc! { "$set": { "deshabilitado": deshabilitado } },
+            None,
+        )
+        .await
+    {
+        Ok(result) if result.matched_count > 0 => {}
+        Ok(_) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    if deshabilitado && cerrar_todas(db, object_id).await.is_err() {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    match coleccion.find_one(doc! { "_id": object_id }, None).await {
+        Ok(Some(usuario)) => Ok(Json(usuario.to_admin())),
+        _ => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+// POST /admin/usuarios/:id/deshabilitar - Deshabilitar una cuenta
+pub async fn deshabilitar_usuario(
+    State(db): State<DbState>,
+    admin: Admin,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    cambiar_estado_usuario(&db, &admin, &id, true).await
+}
+
+// POST /admin/usuarios/:id/habilitar - Volver a habilitar una cuenta
+pub async fn habilitar_usuario(
+    State(db): State<DbState>,
+    admin: Admin,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    cambiar_estado_usuario(&db, &admin, &id, false).await
+}
+
+// GET /admin/usuarios/:id/tareas - Ver las tareas de cualquier usuario
+pub async fn listar_tareas_usuario(
+    State(db): State<DbState>,
+    _admin: Admin,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let usuario_id = ObjectId::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    match obtener_coleccion_usuarios(&db)
+        .find_one(doc! { "_id": usuario_id }, None)
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    match obtener_coleccion_tareas(&db)
+        .find(doc! { "usuario_id": usuario_id }, None)
+        .await
+    {
+        Ok(cursor) => {
+            let tareas: Vec<Tarea> = cursor
+                .try_collect()
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            Ok(Json(tareas.iter().map(Tarea::to_publica).collect::<Vec<_>>()))
+        }
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
@@ -1,182 +1,691 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Query, State},
+    http::{header::RETRY_AFTER, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
-use bcrypt::{hash, verify, DEFAULT_COST};
+use bson::oid::ObjectId;
 use mongodb::bson::doc;
 use validator::Validate;
-use chrono::{Duration, Utc};
-use rand::{distributions::Alphanumeric, Rng};
+use chrono::{DateTime, Duration, Utc};
+use std::sync::OnceLock;
 
 use crate::{
-    db::mongo::{obtener_coleccion_usuarios, DbState},
+    db::mongo::{es_clave_duplicada, obtener_coleccion_usuarios, DbState},
+    handlers::comun::obtener_usuario_id,
+    middleware::cliente::InfoCliente,
+    models::refresh_token::RefreshRequest,
     models::usuario::{
-        AuthResponse, ForgotPasswordRequest, LoginRequest, RegistroRequest,
-        ResetPasswordRequest, Usuario,
+        AuthResponse, ForgotPasswordRequest, LoginRequest, ReenviarVerificacionRequest,
+        RegistroRequest, ResetPasswordRequest, Rol, Usuario, VerificarEmailQuery,
+    },
+    utils::jwt::{self, generar_token, verificar_token_verificacion_email, Claims},
+    utils::mailer::{self, Correo},
+    utils::refresh_token::{emitir_refresh_token, nueva_familia, rotar_refresh_token, Rotacion},
+    utils::intentos_login,
+    utils::password,
+    utils::politica_password,
+    utils::entorno::numero,
+    utils::token_opaco,
+    utils::sesiones::{actualizar_actividad, cerrar_sesion, cerrar_todas, registrar_sesion},
+    utils::verificacion_email::{
+        enviar_email_verificacion, politica_verificacion, PoliticaVerificacion,
     },
-    utils::jwt::generar_token,
 };
 
+// Inicia una sesión nueva: registra el dispositivo y emite token de acceso y refresh token
+pub async fn iniciar_sesion(
+    db: &DbState,
+    user_id: ObjectId,
+    roles: &[Rol],
+    cliente: InfoCliente,
+) -> Result<(String, String), StatusCode> {
+    let sesion = nueva_familia();
+    let token = generar_token(&user_id.to_hex(), &sesion, roles)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let refresh_token = emitir_refresh_token(db, user_id, &sesion)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    registrar_sesion(db, user_id, &sesion, cliente.user_agent, cliente.ip)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((token, refresh_token))
+}
+
+// Hash de una contraseña aleatoria, usado para que un login con un email
+// inexistente tarde lo mismo que uno con contraseña incorrecta
+fn hash_ficticio() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| {
+        password::hashear(&token_opaco::generar(32)).expect("Hash ficticio válido")
+    })
+}
+
 // POST /auth/register - Registrar nuevo usuario
+// La respuesta es la misma exista o no la cuenta; el resultado se comunica por email
 pub async fn registro(
     State(db): State<DbState>,
     Json(payload): Json<RegistroRequest>,
-) -> Result<impl IntoResponse, StatusCode> {
+) -> Result<Response, StatusCode> {
     // Validar datos
-    if let Err(_) = payload.validate() {
+    if payload.validate().is_err() {
         return Ok((
             StatusCode::BAD_REQUEST,
             Json(AuthResponse {
                 success: false,
                 token: None,
+                refresh_token: None,
                 user: None,
                 message: "Datos inválidos".to_string(),
             }),
-        ));
+        )
+            .into_response());
     }
 
     let coleccion = obtener_coleccion_usuarios(&db);
-
-    // Verificar si el email ya existe
     let email_lower = payload.email.to_lowercase();
-    match coleccion
-        .find_one(doc! { "email": &email_lower }, None)
-        .await
+
+    if let Err(errores) = politica_password::validar(&payload.password, &email_lower, &payload.name)
     {
-        Ok(Some(_)) => {
-            return Ok((
-                StatusCode::BAD_REQUEST,
-                Json(AuthResponse {
-                    success: false,
-                    token: None,
-                    user: None,
-                    message: "El email ya está registrado".to_string(),
-                }),
-            ));
-        }
-        Ok(None) => {}
-        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+        return Ok(politica_password::respuesta_rechazo(errores).into_response());
     }
 
-    // Hashear contraseña
-    let password_hash = match hash(&payload.password, DEFAULT_COST) {
+    // Hashear contraseña antes de consultar, para igualar los tiempos de respuesta
+    let password_hash = match password::hashear(&payload.password) {
         Ok(h) => h,
         Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
     };
 
-    // Crear usuario
-    let nuevo_usuario = Usuario::nuevo(payload.name, email_lower, password_hash);
+    match coleccion
+        .find_one(doc! { "email": &email_lower }, None)
+        .await
+    {
+        Ok(Some(existente)) => {
+            // Avisar al titular de la cuenta en lugar de revelar que existe
+            tokio::spawn(async move {
+                let correo = Correo {
+                    para: existente.email.clone(),
+                    asunto: "Intento de registro con tu email".to_string(),
+                    cuerpo: format!(
+                        "Hola {},\n\nAlguien ha intentado crear una cuenta con tu email, \
+                         pero ya tienes una. Si fuiste tú, inicia sesión o restablece tu \
+                         contraseña en:\n\n{}\n\nSi no fuiste tú, puedes ignorar este correo.",
+                        existente.name,
+                        mailer::url("/forgot-password")
+                    ),
+                };
+                if let Err(e) = mailer::enviar(correo).await {
+                    eprintln!("Error al enviar aviso de registro: {}", e);
+                }
+            });
+        }
+        Ok(None) => {
+            let mut nuevo_usuario = Usuario::nuevo(payload.name, email_lower, password_hash);
 
-    // Insertar en base de datos
-    match coleccion.insert_one(&nuevo_usuario, None).await {
-        Ok(result) => {
-            let user_id = result.inserted_id.as_object_id().unwrap();
+            match coleccion.insert_one(&nuevo_usuario, None).await {
+                Ok(result) => {
+                    nuevo_usuario.id = result.inserted_id.as_object_id();
 
-            // Generar token JWT
-            let token = match generar_token(&user_id.to_hex()) {
-                Ok(t) => t,
+                    // Si el email no sale, el usuario puede pedir un reenvío
+                    tokio::spawn(async move {
+                        if let Err(e) = enviar_email_verificacion(&nuevo_usuario).await {
+                            eprintln!("Error al enviar email de verificación: {}", e);
+                        }
+                    });
+                }
+                // Otra petición registró el mismo email a la vez: misma respuesta que si ya existiera
+                Err(e) if es_clave_duplicada(&e) => {}
                 Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-            };
-
-            // Obtener usuario creado
-            match coleccion.find_one(doc! { "_id": user_id }, None).await {
-                Ok(Some(usuario)) => Ok((
-                    StatusCode::CREATED,
-                    Json(AuthResponse {
-                        success: true,
-                        token: Some(token),
-                        user: Some(usuario.to_publico()),
-                        message: "Usuario registrado exitosamente".to_string(),
-                    }),
-                )),
-                _ => Err(StatusCode::INTERNAL_SERVER_ERROR),
             }
         }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(AuthResponse {
+            success: true,
+            token: None,
+            refresh_token: None,
+            user: None,
+            message: "Registro recibido, revisa tu email para continuar".to_string(),
+        }),
+    )
+        .into_response())
+}
+
+// Respuesta 429 con Retry-After mientras dure el bloqueo
+pub fn respuesta_bloqueo(hasta: DateTime<Utc>) -> Response {
+    let segundos = (hasta - Utc::now()).num_seconds().max(1);
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(RETRY_AFTER, segundos.to_string())],
+        Json(AuthResponse {
+            success: false,
+            token: None,
+            refresh_token: None,
+            user: None,
+            message: format!(
+                "Demasiados intentos fallidos, vuelve a intentarlo en {} minutos",
+                (segundos + 59) / 60
+            ),
+        }),
+    )
+        .into_response()
+}
+
+// Primer paso superado con 2FA activo: devuelve el desafío en lugar de los tokens
+pub fn respuesta_desafio_2fa(usuario: &Usuario) -> Result<Response, StatusCode> {
+    let challenge_token = jwt::generar_token_desafio_2fa(&usuario.id.unwrap().to_hex())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "two_factor_required": true,
+            "challenge_token": challenge_token,
+            "message": "Introduce el código de tu app de autenticación"
+        })),
+    )
+        .into_response())
+}
+
+// Aviso al titular cuando su cuenta queda bloqueada por intentos fallidos
+async fn notificar_bloqueo(usuario: &Usuario) -> Result<(), String> {
+    mailer::enviar(Correo {
+        para: usuario.email.clone(),
+        asunto: "Tu cuenta ha sido bloqueada temporalmente".to_string(),
+        cuerpo: format!(
+            "Hola {},\n\nHemos bloqueado temporalmente el inicio de sesión en tu cuenta \
+             tras varios intentos fallidos. Si no fuiste tú, te recomendamos restablecer \
+             tu contraseña en:\n\n{}\n",
+            usuario.name,
+            mailer::url("/forgot-password")
+        ),
+    })
+    .await
 }
 
 // POST /auth/login - Iniciar sesión
 pub async fn login(
     State(db): State<DbState>,
+    cliente: InfoCliente,
     Json(payload): Json<LoginRequest>,
-) -> Result<impl IntoResponse, StatusCode> {
+) -> Result<Response, StatusCode> {
     // Validar datos
-    if let Err(_) = payload.validate() {
+    if payload.validate().is_err() {
         return Ok((
             StatusCode::BAD_REQUEST,
             Json(AuthResponse {
                 success: false,
                 token: None,
+                refresh_token: None,
                 user: None,
                 message: "Datos inválidos".to_string(),
             }),
-        ));
+        )
+            .into_response());
     }
 
     let coleccion = obtener_coleccion_usuarios(&db);
     let email_lower = payload.email.to_lowercase();
 
+    // Rechazar sin verificar la contraseña si la cuenta o la IP están bloqueadas
+    let mut claves = vec![intentos_login::clave_cuenta(&email_lower)];
+    if let Some(ip) = &cliente.ip {
+        claves.push(intentos_login::clave_ip(ip));
+    }
+    match intentos_login::bloqueo_activo(&db, &claves).await {
+        Ok(Some(hasta)) => return Ok(respuesta_bloqueo(hasta)),
+        Ok(None) => {}
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
     // Buscar usuario por email
-    match coleccion
+    let usuario = match coleccion
         .find_one(doc! { "email": &email_lower }, None)
         .await
     {
-        Ok(Some(usuario)) => {
-            // Verificar contraseña
-            match verify(&payload.password, &usuario.password) {
-                Ok(true) => {
-                    // Generar token JWT
-                    let user_id = usuario.id.as_ref().unwrap().to_hex();
-                    let token = match generar_token(&user_id) {
-                        Ok(t) => t,
-                        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-                    };
-
-                    Ok((
-                        StatusCode::OK,
-                        Json(AuthResponse {
-                            success: true,
-                            token: Some(token),
-                            user: Some(usuario.to_publico()),
-                            message: "Inicio de sesión exitoso".to_string(),
-                        }),
-                    ))
+        Ok(usuario) => usuario,
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    // Verificar contraseña (con un hash ficticio si el usuario no existe, mismo coste)
+    let password_valida = match &usuario {
+        Some(usuario) => password::verificar(&payload.password, &usuario.password)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        None => {
+            let _ = password::verificar(&payload.password, hash_ficticio());
+            false
+        }
+    };
+
+    let usuario = match usuario {
+        Some(usuario) if password_valida => usuario,
+        usuario => {
+            let fallo = intentos_login::registrar_fallo(&db, &email_lower, cliente.ip.as_deref())
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            if fallo.cuenta_bloqueada {
+                if let Some(usuario) = usuario {
+                    tokio::spawn(async move {
+                        if let Err(e) = notificar_bloqueo(&usuario).await {
+                            eprintln!("Error al enviar aviso de bloqueo: {}", e);
+                        }
+                    });
+                }
+            }
+
+            // Retraso progresivo para frenar ataques de fuerza bruta
+            tokio::time::sleep(intentos_login::retraso(fallo.fallos)).await;
+
+            return Ok((
+                StatusCode::UNAUTHORIZED,
+                Json(AuthResponse {
+                    success: false,
+                    token: None,
+                    refresh_token: None,
+                    user: None,
+                    message: "Credenciales incorrectas".to_string(),
+                }),
+            )
+                .into_response());
+        }
+    };
+
+    if intentos_login::limpiar_fallos(&db, &intentos_login::clave_cuenta(&email_lower))
+        .await
+        .is_err()
+    {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    // Migración transparente a Argon2id (o a los parámetros actuales) tras un login correcto
+    if password::necesita_rehash(&usuario.password) {
+        match password::hashear(&payload.password) {
+            Ok(nuevo_hash) => {
+                if let Err(e) = coleccion
+                    .update_one(
+                        doc! { "_id": usuario.id, "password": &usuario.password },
+                        doc! { "$set": { "password": nuevo_hash } },
+                        None,
+                    )
+                    .await
+                {
+                    eprintln!("Error al actualizar el hash de la contraseña: {}", e);
                 }
-                Ok(false) => Ok((
-                    StatusCode::UNAUTHORIZED,
-                    Json(AuthResponse {
-                        success: false,
-                        token: None,
-                        user: None,
-                        message: "Credenciales incorrectas".to_string(),
-                    }),
-                )),
-                Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
             }
+            Err(e) => eprintln!("Error al regenerar el hash de la contraseña: {}", e),
         }
-        Ok(None) => Ok((
+    }
+
+    if usuario.deshabilitado {
+        return Ok((
+            StatusCode::FORBIDDEN,
+            Json(AuthResponse {
+                success: false,
+                token: None,
+                refresh_token: None,
+                user: None,
+                message: "La cuenta está deshabilitada".to_string(),
+            }),
+        )
+            .into_response());
+    }
+
+    if !usuario.email_verified && politica_verificacion() == PoliticaVerificacion::Login {
+        return Ok((
+            StatusCode::FORBIDDEN,
+            Json(AuthResponse {
+                success: false,
+                token: None,
+                refresh_token: None,
+                user: None,
+                message: "Debes verificar tu email antes de iniciar sesión".to_string(),
+            }),
+        )
+            .into_response());
+    }
+
+    // Con 2FA activo la contraseña solo da acceso al segundo paso (POST /auth/2fa/verify)
+    if usuario.totp_habilitado {
+        return respuesta_desafio_2fa(&usuario);
+    }
+
+    // Generar token JWT y refresh token
+    let (token, refresh_token) =
+        iniciar_sesion(&db, usuario.id.unwrap(), &usuario.roles, cliente).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(AuthResponse {
+            success: true,
+            token: Some(token),
+            refresh_token: Some(refresh_token),
+            user: Some(usuario.to_publico()),
+            message: "Inicio de sesión exitoso".to_string(),
+        }),
+    )
+        .into_response())
+}
+
+// POST /auth/refresh - Rotar refresh token y obtener un nuevo token de acceso
+pub async fn refresh(
+    State(db): State<DbState>,
+    cliente: InfoCliente,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let rechazo = |message: &str| {
+        (
             StatusCode::UNAUTHORIZED,
             Json(AuthResponse {
                 success: false,
                 token: None,
+                refresh_token: None,
                 user: None,
-                message: "Credenciales incorrectas".to_string(),
+                message: message.to_string(),
             }),
-        )),
+        )
+    };
+
+    match rotar_refresh_token(&db, &payload.refresh_token).await {
+        Ok(Rotacion::Valido {
+            usuario_id,
+            familia,
+            refresh_token,
+        }) => {
+            // Los roles se leen de nuevo por si han cambiado desde el login
+            let usuario = match obtener_coleccion_usuarios(&db)
+                .find_one(doc! { "_id": usuario_id }, None)
+                .await
+            {
+                Ok(Some(usuario)) if !usuario.deshabilitado => usuario,
+                Ok(_) => {
+                    if cerrar_sesion(&db, usuario_id, &familia).await.is_err() {
+                        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                    }
+                    return Ok(rechazo("La cuenta no existe o está deshabilitada"));
+                }
+                Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+            };
+
+            let token = match generar_token(&usuario_id.to_hex(), &familia, &usuario.roles) {
+                Ok(t) => t,
+                Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+            };
+            if actualizar_actividad(&db, &familia, cliente.ip).await.is_err() {
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+
+            Ok((
+                StatusCode::OK,
+                Json(AuthResponse {
+                    success: true,
+                    token: Some(token),
+                    refresh_token: Some(refresh_token),
+                    user: None,
+                    message: "Token renovado".to_string(),
+                }),
+            ))
+        }
+        Ok(Rotacion::Reutilizado {
+            usuario_id,
+            familia,
+        }) => {
+            // Posible robo del token: cerrar la sesión completa
+            if cerrar_sesion(&db, usuario_id, &familia).await.is_err() {
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+            Ok(rechazo(
+                "Refresh token reutilizado, se ha cerrado la sesión asociada",
+            ))
+        }
+        Ok(Rotacion::Invalido) => Ok(rechazo("Refresh token inválido o expirado")),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
+// POST /auth/logout - Cerrar la sesión actual
+pub async fn logout(
+    State(db): State<DbState>,
+    claims: Claims,
+) -> Result<impl IntoResponse, StatusCode> {
+    let usuario_id = obtener_usuario_id(&claims)?;
+
+    // Revocar los tokens de la sesión actual
+    if cerrar_sesion(&db, usuario_id, &claims.sid).await.is_err() {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "message": "Sesión cerrada"
+        })),
+    ))
+}
+
+// POST /auth/logout-all - Cerrar todas las sesiones del usuario
+pub async fn logout_all(
+    State(db): State<DbState>,
+    claims: Claims,
+) -> Result<impl IntoResponse, StatusCode> {
+    let usuario_id = obtener_usuario_id(&claims)?;
+
+    if cerrar_todas(&db, usuario_id).await.is_err() {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "message": "Todas las sesiones han sido cerradas"
+        })),
+    ))
+}
+
+// GET /auth/verify-email?token=... - Confirmar la dirección de email
+pub async fn verificar_email(
+    State(db): State<DbState>,
+    Query(query): Query<VerificarEmailQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let invalido = || {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "Enlace de verificación inválido o expirado"
+            })),
+        )
+    };
+
+    let claims = match verificar_token_verificacion_email(&query.token) {
+        Ok(claims) => claims,
+        Err(_) => return Ok(invalido()),
+    };
+    let usuario_id = match ObjectId::parse_str(&claims.sub) {
+        Ok(id) => id,
+        Err(_) => return Ok(invalido()),
+    };
+
+    let coleccion = obtener_coleccion_usuarios(&db);
+    let verificado = || {
+        (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "message": "Email verificado exitosamente"
+            })),
+        )
+    };
+
+    // El enlace solo vale para la dirección a la que se envió
+    match coleccion
+        .update_one(
+            doc! { "_id": usuario_id, "email": &claims.email },
+            doc! { "$set": { "email_verified": true } },
+            None,
+        )
+        .await
+    {
+        Ok(result) if result.matched_count > 0 => return Ok(verificado()),
+        Ok(_) => {}
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    // Cambio de email pendiente: se aplica al confirmarlo desde la nueva dirección,
+    // salvo que otra cuenta la haya ocupado entretanto (lo impide el índice único)
+    let anterior = match coleccion
+        .find_one_and_update(
+            doc! { "_id": usuario_id, "email_pendiente": &claims.email },
+            doc! {
+                "$set": { "email": &claims.email, "email_verified": true },
+                // Los enlaces de reseteo enviados a la dirección anterior dejan de valer
+                "$unset": { "email_pendiente": "", "reset_token_hash": "", "reset_token_expire": "" },
+            },
+            None,
+        )
+        .await
+    {
+        Ok(Some(anterior)) => anterior,
+        Ok(None) => return Ok(invalido()),
+        Err(e) if es_clave_duplicada(&e) => return Ok(invalido()),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    tokio::spawn(async move {
+        let aviso = Correo {
+            para: anterior.email.clone(),
+            asunto: "El email de tu cuenta ha cambiado".to_string(),
+            cuerpo: format!(
+                "Hola {},\n\nEl email de tu cuenta se ha cambiado a {}. Si no fuiste tú, \
+                 contacta con el soporte.\n",
+                anterior.name, claims.email
+            ),
+        };
+        if let Err(e) = mailer::enviar(aviso).await {
+            eprintln!("Error al enviar aviso de cambio de email: {}", e);
+        }
+    });
+
+    Ok(verificado())
+}
+
+// POST /auth/resend-verification - Reenviar el email de verificación
+pub async fn reenviar_verificacion(
+    State(db): State<DbState>,
+    Json(payload): Json<ReenviarVerificacionRequest>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if payload.validate().is_err() {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "Email inválido"
+            })),
+        ));
+    }
+
+    let email_lower = payload.email.to_lowercase();
+    match obtener_coleccion_usuarios(&db)
+        .find_one(doc! { "email": &email_lower, "email_verified": { "$ne": true } }, None)
+        .await
+    {
+        Ok(Some(usuario)) => {
+            tokio::spawn(async move {
+                if let Err(e) = enviar_email_verificacion(&usuario).await {
+                    eprintln!("Error al enviar email de verificación: {}", e);
+                }
+            });
+        }
+        Ok(None) => {}
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    // Misma respuesta exista o no la cuenta
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "message": "Si la cuenta existe y no está verificada, recibirás un email"
+        })),
+    ))
+}
+
+static DURACION_RESET: OnceLock<Duration> = OnceLock::new();
+
+// Validez de los enlaces de reseteo (RESET_TOKEN_TTL_HOURS, por defecto 24 horas)
+pub fn inicializar_desde_entorno() -> Result<(), String> {
+    let horas = numero("RESET_TOKEN_TTL_HOURS", 24)?;
+    let duracion = Duration::try_hours(horas)
+        .filter(|_| horas > 0)
+        .ok_or_else(|| "RESET_TOKEN_TTL_HOURS debe ser mayor que 0".to_string())?;
+
+    DURACION_RESET
+        .set(duracion)
+        .map_err(|_| "La validez de los enlaces de reseteo ya estaba inicializada".to_string())
+}
+
+fn duracion_reset() -> Duration {
+    *DURACION_RESET.get().expect("Validez de los enlaces de reseteo no inicializada")
+}
+
+// Genera el token de reseteo, guarda su hash y envía el enlace por email
+async fn enviar_enlace_reseteo(db: DbState, usuario: Usuario) -> Result<(), String> {
+    // Token "<id de usuario>.<secreto>"; solo se guarda el hash del secreto
+    let user_id = usuario.id.ok_or("Usuario sin id")?;
+    let secreto = token_opaco::generar(32);
+    let reset_token = format!("{}.{}", user_id.to_hex(), secreto);
+
+    let duracion = duracion_reset();
+    let expire_at = Utc::now()
+        .checked_add_signed(duracion)
+        .expect("Fecha válida");
+
+    // Un nuevo token reemplaza al anterior (y al formato antiguo en texto plano)
+    let update_doc = doc! {
+        "$set": {
+            "reset_token_hash": token_opaco::hash(&secreto),
+            "reset_token_expire": expire_at
+        },
+        "$unset": {
+            "reset_token": ""
+        }
+    };
+
+    obtener_coleccion_usuarios(&db)
+        .update_one(doc! { "_id": user_id }, update_doc, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    mailer::enviar(Correo {
+        para: usuario.email.clone(),
+        asunto: "Restablecer contraseña".to_string(),
+        cuerpo: format!(
+            "Hola {},\n\nPara restablecer tu contraseña abre el siguiente enlace \
+             (válido durante {} horas, un solo uso):\n\n{}\n\n\
+             Si no lo solicitaste, ignora este correo.",
+            usuario.name,
+            duracion.num_hours(),
+            mailer::enlace("/reset-password", &reset_token)
+        ),
+    })
+    .await
+}
+
 // POST /auth/forgot-password - Solicitar reseteo de contraseña
+// La respuesta es la misma exista o no la cuenta; el trabajo se hace en segundo plano
 pub async fn forgot_password(
     State(db): State<DbState>,
     Json(payload): Json<ForgotPasswordRequest>,
 ) -> Result<impl IntoResponse, StatusCode> {
     // Validar datos
-    if let Err(_) = payload.validate() {
+    if payload.validate().is_err() {
         return Ok((
             StatusCode::BAD_REQUEST,
             Json(serde_json::json!({
@@ -186,64 +695,31 @@ pub async fn forgot_password(
         ));
     }
 
-    let coleccion = obtener_coleccion_usuarios(&db);
     let email_lower = payload.email.to_lowercase();
 
     // Buscar usuario por email
-    match coleccion
+    match obtener_coleccion_usuarios(&db)
         .find_one(doc! { "email": &email_lower }, None)
         .await
     {
         Ok(Some(usuario)) => {
-            // Generar token de reseteo aleatorio
-            let reset_token: String = rand::thread_rng()
-                .sample_iter(&Alphanumeric)
-                .take(32)
-                .map(char::from)
-                .collect();
-
-            // Establecer expiración de 24 horas
-            let expire_at = Utc::now()
-                .checked_add_signed(Duration::hours(24))
-                .expect("Fecha válida");
-
-            // Actualizar usuario con token de reseteo
-            let update_doc = doc! {
-                "$set": {
-                    "reset_token": &reset_token,
-                    "reset_token_expire": expire_at
+            tokio::spawn(async move {
+                if let Err(e) = enviar_enlace_reseteo(db, usuario).await {
+                    eprintln!("Error al enviar enlace de reseteo: {}", e);
                 }
-            };
-
-            match coleccion
-                .update_one(
-                    doc! { "_id": usuario.id.unwrap() },
-                    update_doc,
-                    None,
-                )
-                .await
-            {
-                Ok(_) => Ok((
-                    StatusCode::OK,
-                    Json(serde_json::json!({
-                        "success": true,
-                        "message": "Token de reseteo generado",
-                        "reset_token": reset_token,
-                        "note": "En producción, este token se enviaría por email"
-                    })),
-                )),
-                Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-            }
+            });
         }
-        Ok(None) => Ok((
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({
-                "success": false,
-                "message": "Email no encontrado"
-            })),
-        )),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Ok(None) => {}
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "message": "Si el email está registrado, recibirás un enlace para restablecer la contraseña"
+        })),
+    ))
 }
 
 // POST /auth/reset-password - Resetear contraseña con token
@@ -252,7 +728,7 @@ pub async fn reset_password(
     Json(payload): Json<ResetPasswordRequest>,
 ) -> Result<impl IntoResponse, StatusCode> {
     // Validar datos
-    if let Err(_) = payload.validate() {
+    if payload.validate().is_err() {
         return Ok((
             StatusCode::BAD_REQUEST,
             Json(serde_json::json!({
@@ -262,77 +738,97 @@ pub async fn reset_password(
         ));
     }
 
+    let invalido = || {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "Token inválido o expirado"
+            })),
+        )
+    };
+
+    let (user_id, secreto) = match payload
+        .token
+        .split_once('.')
+        .and_then(|(id, secreto)| Some((ObjectId::parse_str(id).ok()?, secreto)))
+    {
+        Some(partes) => partes,
+        None => return Ok(invalido()),
+    };
+
     let coleccion = obtener_coleccion_usuarios(&db);
 
-    // Buscar usuario por token de reseteo
+    let usuario = match coleccion.find_one(doc! { "_id": user_id }, None).await {
+        Ok(Some(usuario)) => usuario,
+        Ok(None) => return Ok(invalido()),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    // Comparar el hash en tiempo constante y comprobar la expiración
+    let hash_guardado = match (&usuario.reset_token_hash, usuario.reset_token_expire) {
+        (Some(hash_guardado), Some(expire_at))
+            if token_opaco::coincide(secreto, hash_guardado) && Utc::now() <= expire_at =>
+        {
+            hash_guardado.clone()
+        }
+        _ => return Ok(invalido()),
+    };
+
+    if let Err(errores) =
+        politica_password::validar(&payload.new_password, &usuario.email, &usuario.name)
+    {
+        return Ok(politica_password::respuesta_rechazo(errores));
+    }
+
+    // Hashear nueva contraseña
+    let password_hash = match password::hashear(&payload.new_password) {
+        Ok(h) => h,
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    // Actualizar contraseña y consumir el token; el filtro por hash impide
+    // que dos peticiones simultáneas usen el mismo enlace
+    let update_doc = doc! {
+        "$set": {
+            "password": password_hash,
+            "sin_password": false
+        },
+        "$unset": {
+            "reset_token": "",
+            "reset_token_hash": "",
+            "reset_token_expire": ""
+        }
+    };
+
     match coleccion
-        .find_one(doc! { "reset_token": &payload.token }, None)
+        .update_one(
+            doc! { "_id": user_id, "reset_token_hash": &hash_guardado },
+            update_doc,
+            None,
+        )
         .await
     {
-        Ok(Some(usuario)) => {
-            // Verificar que el token no haya expirado
-            if let Some(expire_at) = usuario.reset_token_expire {
-                if Utc::now() > expire_at {
-                    return Ok((
-                        StatusCode::BAD_REQUEST,
-                        Json(serde_json::json!({
-                            "success": false,
-                            "message": "El token ha expirado"
-                        })),
-                    ));
-                }
-            } else {
-                return Ok((
-                    StatusCode::BAD_REQUEST,
-                    Json(serde_json::json!({
-                        "success": false,
-                        "message": "Token inválido"
-                    })),
-                ));
-            }
+        Ok(result) if result.matched_count > 0 => {}
+        Ok(_) => return Ok(invalido()),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
 
-            // Hashear nueva contraseña
-            let password_hash = match hash(&payload.new_password, DEFAULT_COST) {
-                Ok(h) => h,
-                Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-            };
+    // Cerrar todas las sesiones abiertas con la contraseña anterior
+    if cerrar_todas(&db, user_id).await.is_err() {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
 
-            // Actualizar contraseña y limpiar token de reseteo
-            let update_doc = doc! {
-                "$set": {
-                    "password": password_hash
-                },
-                "$unset": {
-                    "reset_token": "",
-                    "reset_token_expire": ""
-                }
-            };
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "message": "Contraseña actualizada exitosamente"
+        })),
+    ))
+}
 
-            match coleccion
-                .update_one(
-                    doc! { "_id": usuario.id.unwrap() },
-                    update_doc,
-                    None,
-                )
-                .await
-            {
-                Ok(_) => Ok((
-                    StatusCode::OK,
-                    Json(serde_json::json!({
-                        "success": true,
-                        "message": "Contraseña actualizada exitosamente"
-                    })),
-                )),
-                Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-            }
-        }
-        Ok(None) => Ok((
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({
-                "success": false,
-                "message": "Token inválido"
-            })),
-        )),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+// GET /.well-known/jwks.json - Claves públicas para verificar tokens
+pub async fn jwks() -> impl IntoResponse {
+    Json(jwt::jwks())
 }
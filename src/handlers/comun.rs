@@ -0,0 +1,37 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use bson::oid::ObjectId;
+use mongodb::bson::doc;
+
+use crate::{
+    db::mongo::{obtener_coleccion_usuarios, DbState},
+    models::usuario::Usuario,
+    utils::jwt::Claims,
+};
+
+// Obtiene el ObjectId del usuario autenticado a partir del token
+pub fn obtener_usuario_id(claims: &Claims) -> Result<ObjectId, StatusCode> {
+    ObjectId::parse_str(&claims.sub).map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+pub async fn cargar_usuario(db: &DbState, usuario_id: ObjectId) -> Result<Usuario, StatusCode> {
+    match obtener_coleccion_usuarios(db)
+        .find_one(doc! { "_id": usuario_id }, None)
+        .await
+    {
+        Ok(Some(usuario)) => Ok(usuario),
+        Ok(None) => Err(StatusCode::UNAUTHORIZED),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+pub fn respuesta_error(status: StatusCode, message: &str) -> Response {
+    (
+        status,
+        Json(serde_json::json!({ "success": false, "message": message })),
+    )
+        .into_response()
+}
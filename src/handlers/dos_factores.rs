@@ -0,0 +1,334 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use bson::oid::ObjectId;
+use mongodb::bson::doc;
+
+use crate::{
+    db::mongo::{obtener_coleccion_usuarios, DbState},
+    handlers::auth::{iniciar_sesion, respuesta_bloqueo},
+    handlers::comun::{cargar_usuario, obtener_usuario_id, respuesta_error},
+    middleware::cliente::InfoCliente,
+    models::usuario::{
+        AuthResponse, CodigoDosFactoresRequest, Usuario, VerificarDosFactoresRequest,
+    },
+    utils::intentos_login,
+    utils::jwt::{verificar_token_desafio_2fa, Claims},
+    utils::token_opaco,
+    utils::totp,
+};
+
+// Genera hashes para guardar y devuelve los códigos en claro, que solo se muestran una vez
+fn nuevos_codigos_recuperacion() -> (Vec<String>, Vec<String>) {
+    let codigos = totp::generar_codigos_recuperacion();
+    let hashes = codigos.iter().map(|c| token_opaco::hash(c)).collect();
+    (codigos, hashes)
+}
+
+// Consume un código TOTP (marcando su paso como usado) o un código de recuperación
+async fn consumir_segundo_factor(
+    db: &DbState,
+    usuario: &Usuario,
+    codigo: &str,
+) -> Result<bool, mongodb::error::Error> {
+    let coleccion = obtener_coleccion_usuarios(db);
+    let usuario_id = usuario.id.unwrap();
+
+    if let Some(paso) = usuario
+        .totp_secret
+        .as_deref()
+        .and_then(|secreto| totp::verificar_codigo(secreto, codigo, usuario.totp_ultimo_paso))
+    {
+        // El filtro impide que dos peticiones simultáneas acepten el mismo código
+        let resultado = coleccion
+            .update_one(
+                doc! {
+                    "_id": usuario_id,
+                    "$or": [
+                        { "totp_ultimo_paso": { "$lt": paso } },
+                        { "totp_ultimo_paso": null },
+                    ],
+                },
+                doc! { "$set": { "totp_ultimo_paso": paso } },
+                None,
+            )
+            .await?;
+        return Ok(resultado.modified_count > 0);
+    }
+
+    let hash = token_opaco::hash(&totp::normalizar_codigo_recuperacion(codigo));
+    let resultado = coleccion
+        .update_one(
+            doc! { "_id": usuario_id, "codigos_recuperacion": &hash },
+            doc! { "$pull": { "codigos_recuperacion": &hash } },
+            None,
+        )
+        .await?;
+    Ok(resultado.modified_count > 0)
+}
+
+// Exige un segundo factor válido, con el mismo bloqueo por fallos que el login
+async fn exigir_segundo_factor(
+    db: &DbState,
+    usuario: &Usuario,
+    codigo: &str,
+) -> Result<(), Response> {
+    let usuario_id = usuario.id.unwrap().to_hex();
+    let clave = intentos_login::clave_2fa(&usuario_id);
+
+    match intentos_login::bloqueo_activo(db, std::slice::from_ref(&clave)).await {
+        Ok(Some(hasta)) => return Err(respuesta_bloqueo(hasta)),
+        Ok(None) => {}
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+
+    match consumir_segundo_factor(db, usuario, codigo).await {
+        Ok(true) => {
+            let _ = intentos_login::limpiar_fallos(db, &clave).await;
+            Ok(())
+        }
+        Ok(false) => {
+            let fallo = intentos_login::registrar_fallo_2fa(db, &usuario_id)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+            tokio::time::sleep(intentos_login::retraso(fallo.fallos)).await;
+            Err(respuesta_error(StatusCode::UNAUTHORIZED, "Código incorrecto"))
+        }
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+// POST /auth/2fa/setup - Generar un secreto TOTP pendiente de confirmación
+pub async fn iniciar_configuracion(
+    State(db): State<DbState>,
+    claims: Claims,
+) -> Result<Response, StatusCode> {
+    let usuario_id = obtener_usuario_id(&claims)?;
+    let usuario = cargar_usuario(&db, usuario_id).await?;
+
+    if usuario.totp_habilitado {
+        return Ok(respuesta_error(
+            StatusCode::CONFLICT,
+            "La verificación en dos pasos ya está activada",
+        ));
+    }
+
+    let secreto = totp::generar_secreto();
+    let uri = totp::uri_otpauth(&secreto, &usuario.email)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    obtener_coleccion_usuarios(&db)
+        .update_one(
+            doc! { "_id": usuario_id },
+            doc! { "$set": { "totp_secret_pendiente": &secreto } },
+            None,
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "secret": secreto,
+        "otpauth_uri": uri,
+        "message": "Escanea el código QR y confirma con un código de tu app"
+    }))
+    .into_response())
+}
+
+// POST /auth/2fa/confirm - Activar 2FA con un código del secreto pendiente
+pub async fn confirmar_configuracion(
+    State(db): State<DbState>,
+    claims: Claims,
+    Json(payload): Json<CodigoDosFactoresRequest>,
+) -> Result<Response, StatusCode> {
+    let usuario_id = obtener_usuario_id(&claims)?;
+    let usuario = cargar_usuario(&db, usuario_id).await?;
+
+    let secreto = match usuario.totp_secret_pendiente {
+        Some(secreto) if !usuario.totp_habilitado => secreto,
+        _ => {
+            return Ok(respuesta_error(
+                StatusCode::BAD_REQUEST,
+                "No hay ninguna configuración de 2FA pendiente",
+            ))
+        }
+    };
+
+    let paso = match totp::verificar_codigo(&secreto, &payload.code, None) {
+        Some(paso) => paso,
+        None => return Ok(respuesta_error(StatusCode::BAD_REQUEST, "Código incorrecto")),
+    };
+
+    let (codigos, hashes) = nuevos_codigos_recuperacion();
+
+    // Filtrar por el secreto pendiente evita activar uno reemplazado por otro /setup
+    let resultado = obtener_coleccion_usuarios(&db)
+        .update_one(
+            doc! { "_id": usuario_id, "totp_secret_pendiente": &secreto },
+            doc! {
+                "$set": {
+                    "totp_habilitado": true,
+                    "totp_secret": &secreto,
+                    "totp_ultimo_paso": paso,
+                    "codigos_recuperacion": hashes,
+                },
+                "$unset": { "totp_secret_pendiente": "" },
+            },
+            None,
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if resultado.modified_count == 0 {
+        return Ok(respuesta_error(
+            StatusCode::CONFLICT,
+            "La configuración de 2FA ha cambiado, vuelve a empezar",
+        ));
+    }
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "recovery_codes": codigos,
+        "message": "Verificación en dos pasos activada. Guarda los códigos de recuperación"
+    }))
+    .into_response())
+}
+
+// POST /auth/2fa/disable - Desactivar 2FA con un código TOTP o de recuperación
+pub async fn desactivar(
+    State(db): State<DbState>,
+    claims: Claims,
+    Json(payload): Json<CodigoDosFactoresRequest>,
+) -> Result<Response, StatusCode> {
+    let usuario_id = obtener_usuario_id(&claims)?;
+    let usuario = cargar_usuario(&db, usuario_id).await?;
+
+    if !usuario.totp_habilitado {
+        return Ok(respuesta_error(
+            StatusCode::BAD_REQUEST,
+            "La verificación en dos pasos no está activada",
+        ));
+    }
+
+    if let Err(respuesta) = exigir_segundo_factor(&db, &usuario, &payload.code).await {
+        return Ok(respuesta);
+    }
+
+    obtener_coleccion_usuarios(&db)
+        .update_one(
+            doc! { "_id": usuario_id },
+            doc! {
+                "$set": { "totp_habilitado": false },
+                "$unset": {
+                    "totp_secret": "",
+                    "totp_secret_pendiente": "",
+                    "totp_ultimo_paso": "",
+                    "codigos_recuperacion": "",
+                },
+            },
+            None,
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "Verificación en dos pasos desactivada"
+    }))
+    .into_response())
+}
+
+// POST /auth/2fa/recovery-codes - Regenerar los códigos de recuperación
+pub async fn regenerar_codigos(
+    State(db): State<DbState>,
+    claims: Claims,
+    Json(payload): Json<CodigoDosFactoresRequest>,
+) -> Result<Response, StatusCode> {
+    let usuario_id = obtener_usuario_id(&claims)?;
+    let usuario = cargar_usuario(&db, usuario_id).await?;
+
+    if !usuario.totp_habilitado {
+        return Ok(respuesta_error(
+            StatusCode::BAD_REQUEST,
+            "La verificación en dos pasos no está activada",
+        ));
+    }
+
+    if let Err(respuesta) = exigir_segundo_factor(&db, &usuario, &payload.code).await {
+        return Ok(respuesta);
+    }
+
+    let (codigos, hashes) = nuevos_codigos_recuperacion();
+    obtener_coleccion_usuarios(&db)
+        .update_one(
+            doc! { "_id": usuario_id },
+            doc! { "$set": { "codigos_recuperacion": hashes } },
+            None,
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "recovery_codes": codigos,
+        "message": "Códigos de recuperación regenerados; los anteriores ya no son válidos"
+    }))
+    .into_response())
+}
+
+// POST /auth/2fa/verify - Segundo paso del login: canjear el desafío por los tokens
+pub async fn verificar(
+    State(db): State<DbState>,
+    cliente: InfoCliente,
+    Json(payload): Json<VerificarDosFactoresRequest>,
+) -> Result<Response, StatusCode> {
+    let desafio = match verificar_token_desafio_2fa(&payload.challenge_token) {
+        Ok(desafio) => desafio,
+        Err(_) => {
+            return Ok(respuesta_error(
+                StatusCode::UNAUTHORIZED,
+                "Desafío inválido o expirado, vuelve a iniciar sesión",
+            ))
+        }
+    };
+
+    let usuario_id =
+        ObjectId::parse_str(&desafio.sub).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let usuario = cargar_usuario(&db, usuario_id).await?;
+
+    if usuario.deshabilitado {
+        return Ok(respuesta_error(
+            StatusCode::FORBIDDEN,
+            "La cuenta está deshabilitada",
+        ));
+    }
+
+    if !usuario.totp_habilitado {
+        return Ok(respuesta_error(
+            StatusCode::UNAUTHORIZED,
+            "Desafío inválido o expirado, vuelve a iniciar sesión",
+        ));
+    }
+
+    if let Err(respuesta) = exigir_segundo_factor(&db, &usuario, &payload.code).await {
+        return Ok(respuesta);
+    }
+
+    let (token, refresh_token) =
+        iniciar_sesion(&db, usuario_id, &usuario.roles, cliente).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(AuthResponse {
+            success: true,
+            token: Some(token),
+            refresh_token: Some(refresh_token),
+            user: Some(usuario.to_publico()),
+            message: "Inicio de sesión exitoso".to_string(),
+        }),
+    )
+        .into_response())
+}
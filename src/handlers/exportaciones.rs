@@ -0,0 +1,160 @@
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        StatusCode,
+    },
+    response::IntoResponse,
+    Json,
+};
+use bson::oid::ObjectId;
+use chrono::{Duration, Utc};
+use mongodb::bson::doc;
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    db::mongo::{
+        es_clave_duplicada, obtener_coleccion_exportaciones, obtener_coleccion_usuarios, DbState,
+    },
+    handlers::comun::obtener_usuario_id,
+    models::exportacion::{EstadoExportacion, Exportacion},
+    utils::{exportacion, jwt::Claims},
+};
+
+// Exportación del usuario autenticado que aún no ha caducado
+async fn buscar_exportacion(
+    db: &DbState,
+    claims: &Claims,
+    id: &str,
+) -> Result<Exportacion, StatusCode> {
+    let usuario_id = obtener_usuario_id(claims)?;
+    let object_id = ObjectId::parse_str(id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    match obtener_coleccion_exportaciones(db)
+        .find_one(doc! { "_id": object_id, "usuario_id": usuario_id }, None)
+        .await
+    {
+        Ok(Some(exportacion)) if exportacion.expires_at > Utc::now() => Ok(exportacion),
+        Ok(_) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+// POST /auth/me/exports - Solicitar una exportación de todos los datos personales
+pub async fn solicitar_exportacion(
+    State(db): State<DbState>,
+    claims: Claims,
+) -> Result<impl IntoResponse, StatusCode> {
+    let usuario_id = obtener_usuario_id(&claims)?;
+    let coleccion = obtener_coleccion_exportaciones(&db);
+
+    // Aprovechar la petición para borrar las exportaciones caducadas de todos los usuarios
+    exportacion::eliminar(&db, doc! { "expires_at": { "$lt": Utc::now() } })
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Las pendientes de más de una hora se dan por perdidas (p. ej. por un reinicio del
+    // servidor) para que no bloqueen una nueva
+    let pendiente = bson::to_bson(&EstadoExportacion::Pendiente).unwrap();
+    coleccion
+        .update_many(
+            doc! {
+                "usuario_id": usuario_id,
+                "estado": &pendiente,
+                "created_at": { "$lte": Utc::now() - Duration::hours(1) },
+            },
+            doc! { "$set": {
+                "estado": bson::to_bson(&EstadoExportacion::Fallida).unwrap(),
+                "error": "La exportación no llegó a completarse",
+                "completed_at": Utc::now(),
+            } },
+            None,
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let usuario = match obtener_coleccion_usuarios(&db)
+        .find_one(doc! { "_id": usuario_id }, None)
+        .await
+    {
+        Ok(Some(usuario)) => usuario,
+        Ok(None) => return Err(StatusCode::UNAUTHORIZED),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    let ahora = Utc::now();
+    let mut nueva = Exportacion {
+        id: None,
+        usuario_id,
+        estado: EstadoExportacion::Pendiente,
+        archivo: None,
+        error: None,
+        created_at: ahora,
+        completed_at: None,
+        expires_at: ahora + exportacion::duracion_exportacion(),
+    };
+
+    // Si ya hay una en curso, el índice único rechaza la nueva y se devuelve esa
+    let exportacion_id = match coleccion.insert_one(&nueva, None).await {
+        Ok(result) => result.inserted_id.as_object_id().unwrap(),
+        Err(e) if es_clave_duplicada(&e) => {
+            return match coleccion
+                .find_one(doc! { "usuario_id": usuario_id, "estado": &pendiente }, None)
+                .await
+            {
+                Ok(Some(en_curso)) => Ok((StatusCode::ACCEPTED, Json(en_curso.to_publica()))),
+                // Terminó entre la inserción y la consulta: se puede volver a pedir
+                Ok(None) => Err(StatusCode::CONFLICT),
+                Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+            };
+        }
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    nueva.id = Some(exportacion_id);
+
+    tokio::spawn(exportacion::generar(db.clone(), exportacion_id, usuario));
+
+    Ok((StatusCode::ACCEPTED, Json(nueva.to_publica())))
+}
+
+// GET /auth/me/exports/:id - Consultar el estado de una exportación
+pub async fn estado_exportacion(
+    State(db): State<DbState>,
+    claims: Claims,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let exportacion = buscar_exportacion(&db, &claims, &id).await?;
+    Ok(Json(exportacion.to_publica()))
+}
+
+// GET /auth/me/exports/:id/download - Descargar el archivo JSON generado
+pub async fn descargar_exportacion(
+    State(db): State<DbState>,
+    claims: Claims,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let exportacion = buscar_exportacion(&db, &claims, &id).await?;
+
+    let archivo = match (exportacion.estado, &exportacion.archivo) {
+        (EstadoExportacion::Completada, Some(archivo)) => archivo,
+        _ => return Err(StatusCode::CONFLICT),
+    };
+
+    // Se envía por partes para no cargar en memoria exportaciones grandes
+    let contenido = tokio::fs::File::open(exportacion::directorio().join(archivo))
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let nombre = format!(
+        "attachment; filename=\"datos-{}.json\"",
+        exportacion.created_at.format("%Y%m%d")
+    );
+
+    Ok((
+        [
+            (CONTENT_TYPE, "application/json".to_string()),
+            (CONTENT_DISPOSITION, nombre),
+        ],
+        Body::from_stream(ReaderStream::new(contenido)),
+    ))
+}
@@ -1,2 +1,10 @@
 pub mod tareas;
+pub mod comun;
 pub mod auth;
+pub mod sesiones;
+pub mod admin;
+pub mod dos_factores;
+pub mod oidc;
+pub mod tokens_personales;
+pub mod perfil;
+pub mod exportaciones;
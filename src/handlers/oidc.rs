@@ -0,0 +1,231 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use mongodb::bson::doc;
+
+use crate::{
+    db::mongo::{
+        es_clave_duplicada, obtener_coleccion_estados_oidc, obtener_coleccion_usuarios, DbState,
+    },
+    handlers::auth::{iniciar_sesion, respuesta_desafio_2fa},
+    handlers::comun::respuesta_error,
+    middleware::cliente::InfoCliente,
+    models::oidc::{CallbackOidcQuery, EstadoOidc, IdentidadExterna},
+    models::usuario::{AuthResponse, Usuario},
+    utils::oidc::{self, ClaimsOidc, ErrorOidc},
+    utils::password,
+    utils::token_opaco,
+};
+
+fn respuesta_error_oidc(error: ErrorOidc) -> Response {
+    eprintln!("Error en login OIDC: {}", error);
+    match error {
+        ErrorOidc::Proveedor(_) => respuesta_error(
+            StatusCode::BAD_GATEWAY,
+            "El proveedor de identidad no está disponible",
+        ),
+        ErrorOidc::TokenInvalido(_) => respuesta_error(
+            StatusCode::UNAUTHORIZED,
+            "La respuesta del proveedor de identidad no es válida",
+        ),
+    }
+}
+
+// Busca al usuario por la identidad externa; si no existe, lo vincula por email
+// verificado o crea una cuenta nueva. None si el email es de una cuenta local sin
+// verificar, que no se vincula
+async fn vincular_usuario(
+    db: &DbState,
+    proveedor: &str,
+    claims: &ClaimsOidc,
+    email: String,
+) -> Result<Option<Usuario>, StatusCode> {
+    let coleccion = obtener_coleccion_usuarios(db);
+    let identidad = IdentidadExterna {
+        proveedor: proveedor.to_string(),
+        sub: claims.sub.clone(),
+    };
+    let identidad_bson =
+        bson::to_bson(&identidad).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let por_identidad = doc! { "identidades": { "$elemMatch": &identidad_bson } };
+
+    if let Some(usuario) = coleccion
+        .find_one(por_identidad.clone(), None)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Ok(Some(usuario));
+    }
+
+    if let Some(mut usuario) = coleccion
+        .find_one(doc! { "email": &email }, None)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        // Una cuenta sin verificar pudo registrarla otra persona con una contraseña que
+        // conoce: vincularla le daría acceso a la cuenta del titular del email
+        if !usuario.email_verified {
+            return Ok(None);
+        }
+
+        coleccion
+            .update_one(
+                doc! { "_id": usuario.id },
+                doc! { "$addToSet": { "identidades": &identidad_bson } },
+                None,
+            )
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        usuario.identidades.push(identidad);
+        return Ok(Some(usuario));
+    }
+
+    // Cuenta sin contraseña utilizable: solo entra por el proveedor o tras un reseteo
+    let password_hash = password::hashear(&token_opaco::generar(32))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let nombre = claims
+        .name
+        .clone()
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or_else(|| email.split('@').next().unwrap_or_default().to_string());
+
+    let mut usuario = Usuario::nuevo(nombre, email, password_hash);
+    usuario.email_verified = true;
+    usuario.sin_password = true;
+    usuario.identidades.push(identidad);
+
+    match coleccion.insert_one(&usuario, None).await {
+        Ok(result) => usuario.id = result.inserted_id.as_object_id(),
+        // Otro login simultáneo creó la cuenta: se usa si es de esta misma identidad
+        Err(e) if es_clave_duplicada(&e) => {
+            return coleccion
+                .find_one(por_identidad, None)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+    Ok(Some(usuario))
+}
+
+// GET /auth/oidc/:provider/start - Redirigir al proveedor de identidad
+pub async fn iniciar_login_oidc(
+    State(db): State<DbState>,
+    Path(proveedor): Path<String>,
+) -> Result<Response, StatusCode> {
+    let config = oidc::proveedor(&proveedor).ok_or(StatusCode::NOT_FOUND)?;
+
+    let solicitud = match oidc::url_autorizacion(config).await {
+        Ok(solicitud) => solicitud,
+        Err(e) => return Ok(respuesta_error_oidc(e)),
+    };
+
+    let estado = EstadoOidc {
+        state: solicitud.state,
+        proveedor,
+        code_verifier: solicitud.code_verifier,
+        nonce: solicitud.nonce,
+        expires_at: Utc::now() + Duration::minutes(10),
+    };
+    obtener_coleccion_estados_oidc(&db)
+        .insert_one(&estado, None)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Redirect::to(&solicitud.url).into_response())
+}
+
+// GET /auth/oidc/:provider/callback - Canjear el código y emitir los tokens de la API
+pub async fn callback_oidc(
+    State(db): State<DbState>,
+    Path(proveedor): Path<String>,
+    cliente: InfoCliente,
+    Query(query): Query<CallbackOidcQuery>,
+) -> Result<Response, StatusCode> {
+    let config = oidc::proveedor(&proveedor).ok_or(StatusCode::NOT_FOUND)?;
+
+    if let Some(error) = query.error {
+        let detalle = query.error_description.unwrap_or(error);
+        return Ok(respuesta_error(
+            StatusCode::UNAUTHORIZED,
+            &format!("El proveedor rechazó el inicio de sesión: {}", detalle),
+        ));
+    }
+
+    let (code, state) = match (query.code, query.state) {
+        (Some(code), Some(state)) => (code, state),
+        _ => return Ok(respuesta_error(StatusCode::BAD_REQUEST, "Faltan code o state")),
+    };
+
+    // El state es de un solo uso: se elimina al leerlo
+    let estado = obtener_coleccion_estados_oidc(&db)
+        .find_one_and_delete(doc! { "state": &state, "proveedor": &proveedor }, None)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let estado = match estado {
+        Some(estado) if estado.expires_at > Utc::now() => estado,
+        _ => {
+            return Ok(respuesta_error(
+                StatusCode::BAD_REQUEST,
+                "Inicio de sesión caducado o inválido, vuelve a intentarlo",
+            ))
+        }
+    };
+
+    let claims = match oidc::canjear_codigo(config, &code, &estado.code_verifier, &estado.nonce)
+        .await
+    {
+        Ok(claims) => claims,
+        Err(e) => return Ok(respuesta_error_oidc(e)),
+    };
+
+    let email = match claims.email_verificado() {
+        Some(email) => email,
+        None => {
+            return Ok(respuesta_error(
+                StatusCode::FORBIDDEN,
+                "El proveedor no ha verificado el email de la cuenta",
+            ))
+        }
+    };
+
+    let usuario = match vincular_usuario(&db, &proveedor, &claims, email).await? {
+        Some(usuario) => usuario,
+        None => {
+            return Ok(respuesta_error(
+                StatusCode::CONFLICT,
+                "Ya hay una cuenta sin verificar con este email; verifícala antes de entrar con el proveedor",
+            ))
+        }
+    };
+
+    if usuario.deshabilitado {
+        return Ok(respuesta_error(
+            StatusCode::FORBIDDEN,
+            "La cuenta está deshabilitada",
+        ));
+    }
+
+    if usuario.totp_habilitado {
+        return respuesta_desafio_2fa(&usuario);
+    }
+
+    let (token, refresh_token) =
+        iniciar_sesion(&db, usuario.id.unwrap(), &usuario.roles, cliente).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(AuthResponse {
+            success: true,
+            token: Some(token),
+            refresh_token: Some(refresh_token),
+            user: Some(usuario.to_publico()),
+            message: "Inicio de sesión exitoso".to_string(),
+        }),
+    )
+        .into_response())
+}
@@ -0,0 +1,322 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Duration;
+use mongodb::bson::doc;
+use validator::Validate;
+
+use crate::{
+    db::mongo::{
+        obtener_coleccion_intentos_login, obtener_coleccion_refresh_tokens,
+        obtener_coleccion_tareas, obtener_coleccion_tokens_personales,
+        obtener_coleccion_usuarios, DbState,
+    },
+    handlers::auth::respuesta_bloqueo,
+    handlers::comun::{cargar_usuario, obtener_usuario_id, respuesta_error},
+    middleware::cliente::InfoCliente,
+    models::usuario::{
+        ActualizarPerfilRequest, CambiarPasswordRequest, EliminarCuentaRequest, Usuario,
+    },
+    utils::exportacion,
+    utils::intentos_login,
+    utils::jwt::Claims,
+    utils::password,
+    utils::politica_password,
+    utils::mailer::{self, Correo},
+    utils::sesiones::{cerrar_otras, cerrar_todas, iniciada_hace_menos_de},
+    utils::verificacion_email::enviar_enlace_verificacion,
+};
+
+// Minutos desde el login en los que una cuenta sin contraseña propia puede confirmar
+// operaciones sensibles
+const MINUTOS_REAUTENTICACION: i64 = 10;
+
+// Comprueba la contraseña actual con el mismo bloqueo por fallos que el login,
+// para que un token robado no sirva para adivinarla. Las cuentas creadas por OIDC no
+// tienen contraseña: confirman volviendo a iniciar sesión con su proveedor
+async fn exigir_password(
+    db: &DbState,
+    usuario: &Usuario,
+    password_actual: Option<&str>,
+    claims: &Claims,
+    cliente: &InfoCliente,
+) -> Result<(), Response> {
+    if usuario.sin_password {
+        let reciente = iniciada_hace_menos_de(
+            db,
+            usuario.id.unwrap(),
+            &claims.sid,
+            Duration::minutes(MINUTOS_REAUTENTICACION),
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+
+        return if reciente {
+            Ok(())
+        } else {
+            Err(respuesta_error(
+                StatusCode::UNAUTHORIZED,
+                "Vuelve a iniciar sesión con tu proveedor de identidad para confirmar la operación",
+            ))
+        };
+    }
+
+    let password_actual = password_actual.ok_or_else(|| {
+        respuesta_error(StatusCode::BAD_REQUEST, "Indica la contraseña actual")
+    })?;
+
+    let clave = intentos_login::clave_cuenta(&usuario.email);
+    match intentos_login::bloqueo_activo(db, std::slice::from_ref(&clave)).await {
+        Ok(Some(hasta)) => return Err(respuesta_bloqueo(hasta)),
+        Ok(None) => {}
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+
+    match password::verificar(password_actual, &usuario.password) {
+        Ok(true) => {
+            let _ = intentos_login::limpiar_fallos(db, &clave).await;
+            Ok(())
+        }
+        Ok(false) => {
+            let fallo = intentos_login::registrar_fallo(db, &usuario.email, cliente.ip.as_deref())
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+            tokio::time::sleep(intentos_login::retraso(fallo.fallos)).await;
+            Err(respuesta_error(
+                StatusCode::UNAUTHORIZED,
+                "La contraseña actual es incorrecta",
+            ))
+        }
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+// GET /auth/me - Datos del usuario autenticado
+pub async fn obtener_perfil(
+    State(db): State<DbState>,
+    claims: Claims,
+) -> Result<impl IntoResponse, StatusCode> {
+    let usuario = cargar_usuario(&db, obtener_usuario_id(&claims)?).await?;
+    Ok(Json(usuario.to_publico()))
+}
+
+// PATCH /auth/me - Actualizar nombre y/o email. El email nuevo queda pendiente y solo
+// sustituye al actual cuando se verifica desde la nueva dirección
+pub async fn actualizar_perfil(
+    State(db): State<DbState>,
+    claims: Claims,
+    cliente: InfoCliente,
+    Json(payload): Json<ActualizarPerfilRequest>,
+) -> Result<Response, StatusCode> {
+    if payload.validate().is_err() {
+        return Ok(respuesta_error(StatusCode::BAD_REQUEST, "Datos inválidos"));
+    }
+
+    let coleccion = obtener_coleccion_usuarios(&db);
+    let usuario = cargar_usuario(&db, obtener_usuario_id(&claims)?).await?;
+    let mut set_doc = doc! {};
+    let mut titular_email_nuevo: Option<Usuario> = None;
+
+    if let Some(name) = &payload.name {
+        set_doc.insert("name", name.trim());
+    }
+
+    let email_nuevo = payload
+        .email
+        .map(|email| email.trim().to_lowercase())
+        .filter(|email| *email != usuario.email);
+
+    if let Some(email) = &email_nuevo {
+        // Un token robado no basta para llevarse la cuenta a otra dirección
+        if let Err(respuesta) = exigir_password(
+            &db,
+            &usuario,
+            payload.current_password.as_deref(),
+            &claims,
+            &cliente,
+        )
+        .await
+        {
+            return Ok(respuesta);
+        }
+
+        // Si la dirección ya tiene cuenta la respuesta es la misma, para no revelar qué
+        // emails están registrados; su titular recibe un aviso en vez del enlace
+        titular_email_nuevo = coleccion
+            .find_one(doc! { "email": email }, None)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        // El email actual sigue siendo el de login y el que recibe los enlaces de reseteo
+        set_doc.insert("email_pendiente", email);
+    }
+
+    if set_doc.is_empty() {
+        return Ok(Json(usuario.to_publico()).into_response());
+    }
+
+    coleccion
+        .update_one(doc! { "_id": usuario.id }, doc! { "$set": set_doc }, None)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let actualizado = cargar_usuario(&db, obtener_usuario_id(&claims)?).await?;
+
+    if let Some(email_nuevo) = email_nuevo {
+        let usuario_verificacion = actualizado.clone();
+        tokio::spawn(async move {
+            let resultado = match titular_email_nuevo {
+                Some(titular) => {
+                    mailer::enviar(Correo {
+                        para: titular.email.clone(),
+                        asunto: "Intento de usar tu email en otra cuenta".to_string(),
+                        cuerpo: format!(
+                            "Hola {},\n\nAlguien ha intentado cambiar el email de otra cuenta \
+                             al tuyo. Tu cuenta no se ha modificado y puedes ignorar este \
+                             correo.\n",
+                            titular.name
+                        ),
+                    })
+                    .await
+                }
+                None => enviar_enlace_verificacion(&usuario_verificacion, &email_nuevo).await,
+            };
+            if let Err(e) = resultado {
+                eprintln!("Error al enviar email de verificación: {}", e);
+            }
+
+            // Aviso a la dirección actual por si el cambio no lo pidió el titular
+            let aviso = Correo {
+                para: usuario_verificacion.email.clone(),
+                asunto: "Solicitud de cambio de email".to_string(),
+                cuerpo: format!(
+                    "Hola {},\n\nSe ha solicitado cambiar el email de tu cuenta a {}. El \
+                     cambio solo se aplicará cuando se confirme desde esa dirección. Si no \
+                     fuiste tú, cambia tu contraseña.\n",
+                    usuario_verificacion.name, email_nuevo
+                ),
+            };
+            if let Err(e) = mailer::enviar(aviso).await {
+                eprintln!("Error al enviar aviso de cambio de email: {}", e);
+            }
+        });
+    }
+
+    Ok(Json(actualizado.to_publico()).into_response())
+}
+
+// POST /auth/change-password - Cambiar la contraseña conociendo la actual
+pub async fn cambiar_password(
+    State(db): State<DbState>,
+    claims: Claims,
+    cliente: InfoCliente,
+    Json(payload): Json<CambiarPasswordRequest>,
+) -> Result<Response, StatusCode> {
+    if payload.validate().is_err() {
+        return Ok(respuesta_error(StatusCode::BAD_REQUEST, "Datos inválidos"));
+    }
+
+    let usuario = cargar_usuario(&db, obtener_usuario_id(&claims)?).await?;
+    if let Err(respuesta) = exigir_password(
+        &db,
+        &usuario,
+        payload.current_password.as_deref(),
+        &claims,
+        &cliente,
+    )
+    .await
+    {
+        return Ok(respuesta);
+    }
+
+    if let Err(errores) =
+        politica_password::validar(&payload.new_password, &usuario.email, &usuario.name)
+    {
+        return Ok(politica_password::respuesta_rechazo(errores).into_response());
+    }
+
+    let password_hash =
+        password::hashear(&payload.new_password).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    obtener_coleccion_usuarios(&db)
+        .update_one(
+            doc! { "_id": usuario.id },
+            doc! {
+                "$set": { "password": password_hash, "sin_password": false },
+                "$unset": { "reset_token_hash": "", "reset_token_expire": "" },
+            },
+            None,
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // El resto de dispositivos tienen que volver a iniciar sesión
+    cerrar_otras(&db, usuario.id.unwrap(), &claims.sid)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "Contraseña actualizada; se han cerrado las demás sesiones"
+    }))
+    .into_response())
+}
+
+// DELETE /auth/me - Eliminar la cuenta y todos sus datos
+pub async fn eliminar_cuenta(
+    State(db): State<DbState>,
+    claims: Claims,
+    cliente: InfoCliente,
+    Json(payload): Json<EliminarCuentaRequest>,
+) -> Result<Response, StatusCode> {
+    let usuario = cargar_usuario(&db, obtener_usuario_id(&claims)?).await?;
+    if let Err(respuesta) =
+        exigir_password(&db, &usuario, payload.password.as_deref(), &claims, &cliente).await
+    {
+        return Ok(respuesta);
+    }
+
+    eliminar_datos_usuario(&db, &usuario)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// Borra el usuario y todo lo vinculado a él. Las revocaciones se conservan hasta que
+// caduquen para que los tokens de acceso ya emitidos dejen de valer de inmediato
+async fn eliminar_datos_usuario(db: &DbState, usuario: &Usuario) -> Result<(), mongodb::error::Error> {
+    let usuario_id = usuario.id.unwrap();
+
+    cerrar_todas(db, usuario_id).await?;
+    obtener_coleccion_refresh_tokens(db)
+        .delete_many(doc! { "usuario_id": usuario_id }, None)
+        .await?;
+    obtener_coleccion_tokens_personales(db)
+        .delete_many(doc! { "usuario_id": usuario_id }, None)
+        .await?;
+    obtener_coleccion_tareas(db)
+        .delete_many(doc! { "usuario_id": usuario_id }, None)
+        .await?;
+    obtener_coleccion_intentos_login(db)
+        .delete_many(
+            doc! {
+                "clave": {
+                    "$in": [
+                        intentos_login::clave_cuenta(&usuario.email),
+                        intentos_login::clave_2fa(&usuario_id.to_hex()),
+                    ]
+                }
+            },
+            None,
+        )
+        .await?;
+    exportacion::eliminar(db, doc! { "usuario_id": usuario_id }).await?;
+    obtener_coleccion_usuarios(db)
+        .delete_one(doc! { "_id": usuario_id }, None)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use bson::oid::ObjectId;
+use futures::stream::TryStreamExt;
+use mongodb::{bson::doc, options::FindOptions};
+
+use crate::{
+    db::mongo::{obtener_coleccion_sesiones, DbState},
+    handlers::comun::obtener_usuario_id,
+    models::sesion::{Sesion, SesionPublica},
+    utils::{jwt::Claims, sesiones::cerrar_sesion},
+};
+
+// GET /auth/sessions - Listar las sesiones activas del usuario
+pub async fn listar_sesiones(
+    State(db): State<DbState>,
+    claims: Claims,
+) -> Result<impl IntoResponse, StatusCode> {
+    let usuario_id = obtener_usuario_id(&claims)?;
+    let opciones = FindOptions::builder()
+        .sort(doc! { "last_seen_at": -1 })
+        .build();
+
+    match obtener_coleccion_sesiones(&db)
+        .find(doc! { "usuario_id": usuario_id }, opciones)
+        .await
+    {
+        Ok(cursor) => {
+            let sesiones: Vec<Sesion> = cursor
+                .try_collect()
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            let publicas: Vec<SesionPublica> = sesiones
+                .iter()
+                .map(|s| s.to_publica(&claims.sid))
+                .collect();
+            Ok(Json(publicas))
+        }
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+// DELETE /auth/sessions/:id - Cerrar una sesión concreta (otro dispositivo)
+pub async fn eliminar_sesion(
+    State(db): State<DbState>,
+    claims: Claims,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let usuario_id = obtener_usuario_id(&claims)?;
+    let object_id = ObjectId::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let sesion = match obtener_coleccion_sesiones(&db)
+        .find_one(doc! { "_id": object_id, "usuario_id": usuario_id }, None)
+        .await
+    {
+        Ok(Some(sesion)) => sesion,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    match cerrar_sesion(&db, usuario_id, &sesion.sid).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
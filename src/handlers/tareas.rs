@@ -1,31 +1,76 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Json,
 };
-use bson::oid::ObjectId;
-use mongodb::bson::doc;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use bson::{oid::ObjectId, Document};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::stream::TryStreamExt;
+use mongodb::{bson::doc, options::FindOptions};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    db::mongo::{obtener_coleccion_tareas, DbState},
-    models::tarea::{ActualizarTareaRequest, CrearTareaRequest, Tarea},
+    db::mongo::{obtener_coleccion_tareas, obtener_coleccion_usuarios, DbState},
+    handlers::comun::{obtener_usuario_id, respuesta_error},
+    models::tarea::{
+        ActualizarTareaRequest, BuscarTareasQuery, CampoOrden, CrearTareaRequest, Direccion,
+        ListarTareasQuery, PaginaTareas, Prioridad, ResultadoBusqueda, ResultadosBusqueda, Tarea,
+    },
+    utils::busqueda,
+    utils::jwt::Claims,
+    utils::vencimiento,
+    utils::verificacion_email::{politica_verificacion, PoliticaVerificacion},
 };
 
 // POST /tareas - Crear una nueva tarea
 pub async fn crear_tarea(
     State(db): State<DbState>,
+    claims: Claims,
     Json(payload): Json<CrearTareaRequest>,
-) -> Result<impl IntoResponse, StatusCode> {
+) -> Result<Response, StatusCode> {
     let coleccion = obtener_coleccion_tareas(&db);
+    let usuario_id = obtener_usuario_id(&claims)?;
+
+    // Con la política "tareas" solo se crean tareas con el email verificado
+    if politica_verificacion() == PoliticaVerificacion::Tareas {
+        match obtener_coleccion_usuarios(&db)
+            .find_one(doc! { "_id": usuario_id, "email_verified": true }, None)
+            .await
+        {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return Ok(respuesta_error(
+                    StatusCode::FORBIDDEN,
+                    "Debes verificar tu email antes de crear tareas",
+                ))
+            }
+            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
     
-    let nueva_tarea = Tarea::nueva(payload.descripcion, payload.estado);
+    let mut nueva_tarea = Tarea::nueva(
+        usuario_id,
+        payload.descripcion,
+        payload.estado,
+        payload.prioridad,
+        payload.idioma.unwrap_or_default(),
+    );
+    if let Some(fecha) = &payload.fecha_vencimiento {
+        match vencimiento::interpretar(fecha, payload.zona_horaria.as_deref()) {
+            Ok(v) => nueva_tarea.vencimiento = Some(v),
+            Err(mensaje) => return Ok(respuesta_error(StatusCode::BAD_REQUEST, &mensaje)),
+        }
+    }
     
     match coleccion.insert_one(nueva_tarea, None).await {
         Ok(result) => {
             let id = result.inserted_id.as_object_id().unwrap();
             match coleccion.find_one(doc! { "_id": id }, None).await {
-                Ok(Some(tarea)) => Ok((StatusCode::CREATED, Json(tarea))),
+                Ok(Some(tarea)) => {
+                    Ok((StatusCode::CREATED, Json(tarea.to_publica())).into_response())
+                }
                 _ => Err(StatusCode::INTERNAL_SERVER_ERROR),
             }
         }
@@ -33,37 +78,392 @@ pub async fn crear_tarea(
     }
 }
 
-// GET /tareas - Listar todas las tareas
+// Límite máximo de tareas por página
+const LIMITE_MAXIMO: u64 = 100;
+
+// Convierte `desde`/`hasta` en una condición sobre fecha_creacion. Una fecha sin hora
+// abarca el día completo (UTC)
+fn condicion_fecha(valor: &str, es_hasta: bool) -> Option<(&'static str, DateTime<Utc>)> {
+    if let Ok(fecha) = DateTime::parse_from_rfc3339(valor) {
+        let operador = if es_hasta { "$lte" } else { "$gte" };
+        return Some((operador, fecha.with_timezone(&Utc)));
+    }
+
+    let dia = NaiveDate::parse_from_str(valor, "%Y-%m-%d").ok()?;
+    if es_hasta {
+        let siguiente = dia.succ_opt()?.and_hms_opt(0, 0, 0)?.and_utc();
+        Some(("$lt", siguiente))
+    } else {
+        Some(("$gte", dia.and_hms_opt(0, 0, 0)?.and_utc()))
+    }
+}
+
+// Posición tras la última tarea devuelta. Se entrega codificado (base64 de JSON) para
+// que el cliente lo trate como un valor opaco
+#[derive(Serialize, Deserialize)]
+struct CursorTareas {
+    f: i64,     // fecha_creacion en milisegundos
+    id: String, // _id en hexadecimal
+    o: Direccion,
+}
+
+fn codificar_cursor(tarea: &Tarea, direccion: Direccion) -> Option<String> {
+    let cursor = CursorTareas {
+        f: tarea.fecha_creacion.timestamp_millis(),
+        id: tarea.id?.to_hex(),
+        o: direccion,
+    };
+    serde_json::to_vec(&cursor)
+        .ok()
+        .map(|json| URL_SAFE_NO_PAD.encode(json))
+}
+
+fn decodificar_cursor(valor: &str) -> Option<(bson::DateTime, ObjectId, Direccion)> {
+    let json = URL_SAFE_NO_PAD.decode(valor).ok()?;
+    let cursor: CursorTareas = serde_json::from_slice(&json).ok()?;
+    let id = ObjectId::parse_str(&cursor.id).ok()?;
+    Some((bson::DateTime::from_millis(cursor.f), id, cursor.o))
+}
+
+// Enlace relativo a otra página conservando filtros y orden
+fn enlace_pagina(query: &ListarTareasQuery, offset: u64, cursor: Option<String>) -> Option<String> {
+    let mut query = query.clone();
+    query.offset = offset;
+    query.cursor = cursor;
+    serde_urlencoded::to_string(&query)
+        .ok()
+        .map(|parametros| format!("/tareas?{}", parametros))
+}
+
+// Etapas de agregación para sort=prioridad. Las prioridades se guardan como texto, así
+// que se ordena por su peso; después va el vencimiento más próximo (las tareas sin fecha
+// al final) y, a igualdad, las creadas más recientemente
+fn orden_prioridad(direccion: i32) -> Vec<Document> {
+    let ramas: Vec<Document> = Prioridad::TODAS
+        .iter()
+        .map(|prioridad| {
+            doc! {
+                "case": { "$eq": ["$prioridad", bson::to_bson(prioridad).unwrap()] },
+                "then": prioridad.peso(),
+            }
+        })
+        .collect();
+
+    vec![
+        doc! {
+            "$addFields": {
+                "_peso_prioridad": {
+                    "$switch": { "branches": ramas, "default": Prioridad::default().peso() }
+                },
+                "_sin_vencimiento": {
+                    "$eq": [{ "$ifNull": ["$vencimiento.fecha", null] }, null]
+                },
+            }
+        },
+        doc! {
+            "$sort": {
+                "_peso_prioridad": direccion,
+                "_sin_vencimiento": 1,
+                "vencimiento.fecha": 1,
+                "fecha_creacion": -1,
+                "_id": -1,
+            }
+        },
+        doc! { "$unset": ["_peso_prioridad", "_sin_vencimiento"] },
+    ]
+}
+
+// GET /tareas - Listar las tareas del usuario autenticado (filtros, orden y paginación)
 pub async fn listar_tareas(
     State(db): State<DbState>,
-) -> Result<impl IntoResponse, StatusCode> {
+    claims: Claims,
+    Query(mut query): Query<ListarTareasQuery>,
+) -> Result<Response, StatusCode> {
     let coleccion = obtener_coleccion_tareas(&db);
-    
-    use futures::stream::StreamExt;
-    
-    match coleccion.find(None, None).await {
-        Ok(mut cursor) => {
-            let tareas: Vec<Tarea> = cursor
-                .filter_map(|result| async move { result.ok() })
-                .collect()
-                .await;
-            Ok(Json(tareas))
+    let usuario_id = obtener_usuario_id(&claims)?;
+    query.limit = query.limit.clamp(1, LIMITE_MAXIMO);
+
+    let mut filtro = doc! { "usuario_id": usuario_id };
+    if let Some(estado) = &query.estado {
+        filtro.insert("estado", bson::to_bson(estado).unwrap());
+    }
+    if let Some(prioridad) = &query.prioridad {
+        filtro.insert("prioridad", bson::to_bson(prioridad).unwrap());
+    }
+
+    let mut rango = doc! {};
+    for (valor, es_hasta) in [(&query.desde, false), (&query.hasta, true)] {
+        if let Some(valor) = valor {
+            match condicion_fecha(valor, es_hasta) {
+                Some((operador, fecha)) => {
+                    rango.insert(operador, bson::DateTime::from_chrono(fecha));
+                }
+                None => {
+                    return Ok(respuesta_error(
+                        StatusCode::BAD_REQUEST,
+                        "Fecha inválida: usa RFC 3339 o AAAA-MM-DD",
+                    ))
+                }
+            }
         }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
+    if !rango.is_empty() {
+        filtro.insert("fecha_creacion", rango);
+    }
+
+    if let Some(vista) = query.vista {
+        let condicion = vencimiento::zona(query.tz.as_deref())
+            .and_then(|tz| vencimiento::filtro_vista(vista, tz, Utc::now()));
+        match condicion {
+            // En $and para no chocar con el $or de la paginación por cursor
+            Ok(condicion) => {
+                filtro.insert("$and", vec![condicion]);
+            }
+            Err(mensaje) => return Ok(respuesta_error(StatusCode::BAD_REQUEST, &mensaje)),
+        }
+    }
+
+    let campo = match query.sort {
+        CampoOrden::FechaCreacion => "fecha_creacion",
+        CampoOrden::FechaVencimiento => "vencimiento.fecha",
+        CampoOrden::Descripcion => "descripcion",
+        CampoOrden::Estado => "estado",
+        CampoOrden::Prioridad => "prioridad", // Se ordena por su peso (ver orden_prioridad)
+    };
+    let direccion = match query.order {
+        Direccion::Asc => 1,
+        Direccion::Desc => -1,
+    };
+
+    // Contar recorre todas las tareas del filtro: solo se hace en la primera página de
+    // una paginación por cursor, que es la que se usa en las listas grandes
+    let total = if query.cursor.is_none() {
+        Some(
+            coleccion
+                .count_documents(filtro.clone(), None)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        )
+    } else {
+        None
+    };
+
+    // Con cursor se continúa justo después de la última tarea vista, sin saltar
+    // documentos: el coste no crece con la página y las inserciones no desplazan resultados
+    if let Some(cursor) = &query.cursor {
+        let (fecha, id, orden) = match decodificar_cursor(cursor) {
+            Some(posicion) => posicion,
+            None => return Ok(respuesta_error(StatusCode::BAD_REQUEST, "Cursor inválido")),
+        };
+        if query.sort != CampoOrden::FechaCreacion || orden != query.order {
+            return Ok(respuesta_error(
+                StatusCode::BAD_REQUEST,
+                "El cursor solo admite sort=fecha_creacion con el mismo order",
+            ));
+        }
+
+        let operador = if direccion == 1 { "$gt" } else { "$lt" };
+        filtro.insert(
+            "$or",
+            vec![
+                doc! { "fecha_creacion": { operador: fecha } },
+                doc! { "fecha_creacion": fecha, "_id": { operador: id } },
+            ],
+        );
+        query.offset = 0;
+    }
+
+    // _id desempata para que el orden sea estable entre páginas. Se pide una tarea de
+    // más para saber si hay página siguiente
+    let mut tareas: Vec<Tarea> = if query.sort == CampoOrden::Prioridad {
+        let mut pipeline = vec![doc! { "$match": filtro }];
+        pipeline.extend(orden_prioridad(direccion));
+        pipeline.push(doc! { "$skip": query.offset as i64 });
+        pipeline.push(doc! { "$limit": query.limit as i64 + 1 });
+
+        let documentos: Vec<Document> = coleccion
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .try_collect()
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        documentos
+            .into_iter()
+            .map(bson::from_document)
+            .collect::<Result<_, _>>()
+            .map_err(|e| {
+                eprintln!("Error al leer las tareas de {}: {}", usuario_id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+    } else {
+        let opciones = FindOptions::builder()
+            .sort(doc! { campo: direccion, "_id": direccion })
+            .skip(query.offset)
+            .limit(query.limit as i64 + 1)
+            .build();
+
+        coleccion
+            .find(filtro, opciones)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .try_collect()
+            .await
+            .map_err(|e| {
+                eprintln!("Error al leer las tareas de {}: {}", usuario_id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+    };
+
+    let hay_mas = tareas.len() as u64 > query.limit;
+    tareas.truncate(query.limit as usize);
+
+    let next_cursor = match (hay_mas, query.sort, tareas.last()) {
+        (true, CampoOrden::FechaCreacion, Some(ultima)) => codificar_cursor(ultima, query.order),
+        _ => None,
+    };
+
+    // En modo cursor solo se enlaza hacia delante
+    let (next, prev) = if query.cursor.is_some() {
+        let next = next_cursor
+            .clone()
+            .and_then(|cursor| enlace_pagina(&query, 0, Some(cursor)));
+        (next, None)
+    } else {
+        let next = if hay_mas {
+            enlace_pagina(&query, query.offset + query.limit, None)
+        } else {
+            None
+        };
+        let prev = if query.offset > 0 {
+            enlace_pagina(&query, query.offset.saturating_sub(query.limit), None)
+        } else {
+            None
+        };
+        (next, prev)
+    };
+
+    Ok(Json(PaginaTareas {
+        data: tareas.iter().map(Tarea::to_publica).collect(),
+        total,
+        limit: query.limit,
+        offset: query.offset,
+        next,
+        prev,
+        next_cursor,
+    })
+    .into_response())
+}
+
+// Longitud máxima de la consulta de búsqueda
+const LONGITUD_MAXIMA_CONSULTA: usize = 200;
+
+// GET /tareas/search?q= - Buscar en la descripción de las tareas, por relevancia
+pub async fn buscar_tareas(
+    State(db): State<DbState>,
+    claims: Claims,
+    Query(query): Query<BuscarTareasQuery>,
+) -> Result<Response, StatusCode> {
+    let usuario_id = obtener_usuario_id(&claims)?;
+    let consulta = query.q.trim();
+    if consulta.is_empty() || consulta.chars().count() > LONGITUD_MAXIMA_CONSULTA {
+        return Ok(respuesta_error(
+            StatusCode::BAD_REQUEST,
+            "La búsqueda debe tener entre 1 y 200 caracteres",
+        ));
+    }
+    let limit = query.limit.clamp(1, LIMITE_MAXIMO);
+
+    // La consulta se reduce a raíces con las reglas de su idioma
+    let idioma = query.idioma.unwrap_or_default();
+    let mut filtro = doc! {
+        "usuario_id": usuario_id,
+        "$text": { "$search": consulta, "$language": bson::to_bson(&idioma).unwrap() },
+    };
+    if let Some(estado) = &query.estado {
+        filtro.insert("estado", bson::to_bson(estado).unwrap());
+    }
+
+    // Colección sin tipar para recibir también la puntuación de relevancia
+    let coleccion = db.collection::<Document>("tareas");
+    let total = coleccion
+        .count_documents(filtro.clone(), None)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let opciones = FindOptions::builder()
+        .projection(doc! { "score": { "$meta": "textScore" } })
+        .sort(doc! { "score": { "$meta": "textScore" }, "fecha_creacion": -1, "_id": -1 })
+        .skip(query.offset)
+        .limit(limit as i64 + 1) // Una de más para saber si hay página siguiente
+        .build();
+
+    let mut documentos: Vec<Document> = coleccion
+        .find(filtro, opciones)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .try_collect()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let hay_mas = documentos.len() as u64 > limit;
+    documentos.truncate(limit as usize);
+
+    let enlace = |offset: u64| {
+        let mut query = query.clone();
+        query.limit = limit;
+        query.offset = offset;
+        serde_urlencoded::to_string(&query)
+            .ok()
+            .map(|parametros| format!("/tareas/search?{}", parametros))
+    };
+    let next = if hay_mas { enlace(query.offset + limit) } else { None };
+    let prev = if query.offset > 0 {
+        enlace(query.offset.saturating_sub(limit))
+    } else {
+        None
+    };
+
+    let terminos = busqueda::terminos(consulta);
+    let mut resultados = Vec::with_capacity(documentos.len());
+    for documento in documentos {
+        let score = documento.get_f64("score").unwrap_or_default();
+        let tarea: Tarea = bson::from_document(documento).map_err(|e| {
+            eprintln!("Error al leer una tarea de {}: {}", usuario_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        resultados.push(ResultadoBusqueda {
+            snippet: busqueda::fragmento(&tarea.descripcion, &terminos),
+            score,
+            tarea: tarea.to_publica(),
+        });
+    }
+
+    Ok(Json(ResultadosBusqueda {
+        data: resultados,
+        total,
+        limit,
+        offset: query.offset,
+        next,
+        prev,
+    })
+    .into_response())
 }
 
 // GET /tareas/:id - Obtener una tarea por ID
 pub async fn obtener_tarea(
     State(db): State<DbState>,
+    claims: Claims,
     Path(id): Path<String>,
 ) -> Result<impl IntoResponse, StatusCode> {
     let coleccion = obtener_coleccion_tareas(&db);
     
+    let usuario_id = obtener_usuario_id(&claims)?;
     let object_id = ObjectId::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let filtro = doc! { "_id": object_id, "usuario_id": usuario_id };
     
-    match coleccion.find_one(doc! { "_id": object_id }, None).await {
-        Ok(Some(tarea)) => Ok(Json(tarea)),
+    match coleccion.find_one(filtro, None).await {
+        Ok(Some(tarea)) => Ok(Json(tarea.to_publica())),
         Ok(None) => Err(StatusCode::NOT_FOUND),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
@@ -72,15 +472,22 @@ pub async fn obtener_tarea(
 // PUT /tareas/:id - Actualizar una tarea
 pub async fn actualizar_tarea(
     State(db): State<DbState>,
+    claims: Claims,
     Path(id): Path<String>,
     Json(payload): Json<ActualizarTareaRequest>,
-) -> Result<impl IntoResponse, StatusCode> {
+) -> Result<Response, StatusCode> {
     let coleccion = obtener_coleccion_tareas(&db);
     
+    let usuario_id = obtener_usuario_id(&claims)?;
     let object_id = ObjectId::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let filtro = doc! { "_id": object_id, "usuario_id": usuario_id };
     
     let mut update_doc = doc! {};
     
+    if let Some(idioma) = payload.idioma {
+        update_doc.insert("idioma", bson::to_bson(&idioma).unwrap());
+    }
+
     if let Some(descripcion) = payload.descripcion {
         update_doc.insert("descripcion", descripcion);
     }
@@ -88,18 +495,52 @@ pub async fn actualizar_tarea(
     if let Some(estado) = payload.estado {
         update_doc.insert("estado", bson::to_bson(&estado).unwrap());
     }
+
+    if let Some(prioridad) = payload.prioridad {
+        update_doc.insert("prioridad", bson::to_bson(&prioridad).unwrap());
+    }
     
-    if update_doc.is_empty() {
+    let mut unset_doc = doc! {};
+    match (payload.fecha_vencimiento, &payload.zona_horaria) {
+        (Some(Some(fecha)), zona_horaria) => {
+            match vencimiento::interpretar(&fecha, zona_horaria.as_deref()) {
+                Ok(v) => {
+                    update_doc.insert("vencimiento", bson::to_bson(&v).unwrap());
+                }
+                Err(mensaje) => return Ok(respuesta_error(StatusCode::BAD_REQUEST, &mensaje)),
+            }
+        }
+        (Some(None), _) => {
+            unset_doc.insert("vencimiento", "");
+        }
+        (None, Some(_)) => {
+            return Ok(respuesta_error(
+                StatusCode::BAD_REQUEST,
+                "zona_horaria solo se admite junto a fecha_vencimiento",
+            ))
+        }
+        (None, None) => {}
+    }
+    
+    if update_doc.is_empty() && unset_doc.is_empty() {
         return Err(StatusCode::BAD_REQUEST);
     }
     
+    let mut cambios = doc! {};
+    if !update_doc.is_empty() {
+        cambios.insert("$set", update_doc);
+    }
+    if !unset_doc.is_empty() {
+        cambios.insert("$unset", unset_doc);
+    }
+    
     match coleccion
-        .update_one(doc! { "_id": object_id }, doc! { "$set": update_doc }, None)
+        .update_one(filtro.clone(), cambios, None)
         .await
     {
         Ok(result) if result.matched_count > 0 => {
-            match coleccion.find_one(doc! { "_id": object_id }, None).await {
-                Ok(Some(tarea)) => Ok(Json(tarea)),
+            match coleccion.find_one(filtro, None).await {
+                Ok(Some(tarea)) => Ok(Json(tarea.to_publica()).into_response()),
                 _ => Err(StatusCode::INTERNAL_SERVER_ERROR),
             }
         }
@@ -111,15 +552,80 @@ pub async fn actualizar_tarea(
 // DELETE /tareas/:id - Eliminar una tarea
 pub async fn eliminar_tarea(
     State(db): State<DbState>,
+    claims: Claims,
     Path(id): Path<String>,
 ) -> Result<impl IntoResponse, StatusCode> {
     let coleccion = obtener_coleccion_tareas(&db);
     
+    let usuario_id = obtener_usuario_id(&claims)?;
     let object_id = ObjectId::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let filtro = doc! { "_id": object_id, "usuario_id": usuario_id };
     
-    match coleccion.delete_one(doc! { "_id": object_id }, None).await {
+    match coleccion.delete_one(filtro, None).await {
         Ok(result) if result.deleted_count > 0 => Ok(StatusCode::NO_CONTENT),
         Ok(_) => Err(StatusCode::NOT_FOUND),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tarea::{EstadoTarea, Idioma};
+
+    fn tarea_guardada() -> Tarea {
+        let mut tarea = Tarea::nueva(
+            ObjectId::new(),
+            "Comprar pan".to_string(),
+            EstadoTarea::Pendiente,
+            Prioridad::Media,
+            Idioma::Es,
+        );
+        tarea.id = Some(ObjectId::new());
+        tarea
+    }
+
+    #[test]
+    fn el_cursor_conserva_la_posicion() {
+        let tarea = tarea_guardada();
+        for direccion in [Direccion::Asc, Direccion::Desc] {
+            let cursor = codificar_cursor(&tarea, direccion).unwrap();
+            let (fecha, id, orden) = decodificar_cursor(&cursor).unwrap();
+            assert_eq!(fecha.timestamp_millis(), tarea.fecha_creacion.timestamp_millis());
+            assert_eq!(Some(id), tarea.id);
+            assert_eq!(orden, direccion);
+        }
+    }
+
+    #[test]
+    fn sin_id_no_hay_cursor() {
+        let mut tarea = tarea_guardada();
+        tarea.id = None;
+        assert!(codificar_cursor(&tarea, Direccion::Desc).is_none());
+    }
+
+    #[test]
+    fn rechaza_cursores_que_no_son_base64_de_json() {
+        for valor in ["", "no es un cursor", "%%%", "eyJmIjox", &URL_SAFE_NO_PAD.encode("hola")] {
+            assert!(decodificar_cursor(valor).is_none(), "aceptado: {:?}", valor);
+        }
+    }
+
+    #[test]
+    fn rechaza_cursores_manipulados() {
+        let invalidos = [
+            r#"{"f":1,"id":"no-es-un-id","o":"desc"}"#,
+            r#"{"f":1,"id":"65a1b2c3d4e5f6a7b8c9d0e1","o":"lateral"}"#,
+            r#"{"f":"ayer","id":"65a1b2c3d4e5f6a7b8c9d0e1","o":"asc"}"#,
+            r#"{"id":"65a1b2c3d4e5f6a7b8c9d0e1","o":"asc"}"#,
+        ];
+        for json in invalidos {
+            let cursor = URL_SAFE_NO_PAD.encode(json);
+            assert!(decodificar_cursor(&cursor).is_none(), "aceptado: {}", json);
+        }
+
+        // Un cursor truncado tampoco se acepta
+        let cursor = codificar_cursor(&tarea_guardada(), Direccion::Asc).unwrap();
+        assert!(decodificar_cursor(&cursor[..cursor.len() - 4]).is_none());
+    }
+}
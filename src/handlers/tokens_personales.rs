@@ -0,0 +1,119 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use bson::oid::ObjectId;
+use chrono::{Duration, Utc};
+use futures::stream::TryStreamExt;
+use mongodb::{bson::doc, options::FindOptions};
+use validator::Validate;
+
+use crate::{
+    db::mongo::{obtener_coleccion_tokens_personales, DbState},
+    handlers::comun::obtener_usuario_id,
+    models::token_personal::{CrearTokenPersonalRequest, TokenPersonal, TokenPersonalPublico},
+    utils::{jwt::Claims, token_opaco, tokens_personales},
+};
+
+// GET /auth/tokens - Listar los tokens de acceso personal del usuario
+pub async fn listar_tokens(
+    State(db): State<DbState>,
+    claims: Claims,
+) -> Result<impl IntoResponse, StatusCode> {
+    let usuario_id = obtener_usuario_id(&claims)?;
+    let opciones = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+
+    match obtener_coleccion_tokens_personales(&db)
+        .find(doc! { "usuario_id": usuario_id }, opciones)
+        .await
+    {
+        Ok(cursor) => {
+            let tokens: Vec<TokenPersonal> = cursor
+                .try_collect()
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            let publicos: Vec<TokenPersonalPublico> =
+                tokens.iter().map(|t| t.to_publico()).collect();
+            Ok(Json(publicos))
+        }
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+// POST /auth/tokens - Crear un token; el valor solo se devuelve en esta respuesta
+pub async fn crear_token(
+    State(db): State<DbState>,
+    claims: Claims,
+    Json(payload): Json<CrearTokenPersonalRequest>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if payload.validate().is_err() {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "Datos inválidos"
+            })),
+        ));
+    }
+
+    let usuario_id = obtener_usuario_id(&claims)?;
+    let token = tokens_personales::generar();
+    let ahora = Utc::now();
+
+    let mut scopes = Vec::new();
+    for scope in payload.scopes {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+
+    let mut token_personal = TokenPersonal {
+        id: None,
+        usuario_id,
+        nombre: payload.name.trim().to_string(),
+        token_hash: token_opaco::hash(&token),
+        scopes,
+        created_at: ahora,
+        expires_at: payload.expires_in_days.map(|dias| ahora + Duration::days(dias)),
+        last_used_at: None,
+    };
+
+    match obtener_coleccion_tokens_personales(&db)
+        .insert_one(&token_personal, None)
+        .await
+    {
+        Ok(result) => token_personal.id = result.inserted_id.as_object_id(),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "success": true,
+            "token": token,
+            "data": token_personal.to_publico(),
+            "message": "Guarda el token ahora, no se volverá a mostrar"
+        })),
+    ))
+}
+
+// DELETE /auth/tokens/:id - Revocar un token de acceso personal
+pub async fn eliminar_token(
+    State(db): State<DbState>,
+    claims: Claims,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let usuario_id = obtener_usuario_id(&claims)?;
+    let object_id = ObjectId::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    match obtener_coleccion_tokens_personales(&db)
+        .delete_one(doc! { "_id": object_id, "usuario_id": usuario_id }, None)
+        .await
+    {
+        Ok(result) if result.deleted_count > 0 => Ok(StatusCode::NO_CONTENT),
+        Ok(_) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
@@ -1,10 +1,11 @@
 mod db;
 mod handlers;
+mod middleware;
 mod models;
 mod routes;
 mod utils;
 
-use axum::Router;
+use axum::{http::header, Router};
 use db::mongo::conectar_mongodb;
 use routes::crear_rutas;
 use std::net::SocketAddr;
@@ -12,9 +13,39 @@ use tower_http::cors::CorsLayer;
 
 #[tokio::main]
 async fn main() {
+    // Cargar claves JWT antes de aceptar peticiones
+    utils::jwt::inicializar_desde_entorno().expect("Error en la configuración JWT");
+    utils::mailer::inicializar_desde_entorno().expect("Error en la configuración de correo");
+    utils::oidc::inicializar_desde_entorno().expect("Error en la configuración OIDC");
+    utils::totp::inicializar_desde_entorno().expect("Error en la configuración TOTP");
+    utils::exportacion::inicializar_desde_entorno()
+        .expect("Error en la configuración de exportaciones");
+    utils::refresh_token::inicializar_desde_entorno()
+        .expect("Error en la duración de los refresh tokens");
+    utils::password::inicializar_desde_entorno().expect("Error en la configuración de Argon2");
+    utils::politica_password::inicializar_desde_entorno()
+        .expect("Error en la política de contraseñas");
+    utils::intentos_login::inicializar_desde_entorno()
+        .expect("Error en la configuración del bloqueo de login");
+    utils::verificacion_email::inicializar_desde_entorno()
+        .expect("Error en la política de verificación de email");
+    handlers::auth::inicializar_desde_entorno()
+        .expect("Error en la validez de los enlaces de reseteo");
+    middleware::cliente::inicializar_desde_entorno()
+        .expect("Error en la configuración del proxy");
+
     // Conectar a MongoDB
     let db = conectar_mongodb().await.expect("Error al conectar con MongoDB");
     println!("✓ Conectado a MongoDB");
+    db::mongo::crear_indices(&db)
+        .await
+        .expect("Error al crear índices en MongoDB");
+    db::mongo::migrar_tareas(&db)
+        .await
+        .expect("Error al migrar las tareas");
+    db::mongo::promover_admin_inicial(&db)
+        .await
+        .expect("Error al asignar el administrador inicial");
 
     // Crear rutas con el estado compartido
     use tower_http::cors::Any;
@@ -22,7 +53,8 @@ async fn main() {
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
-        .allow_headers(Any);
+        // El comodín no cubre Authorization: hay que nombrarla para que el navegador la envíe
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE]);
     
     let app = Router::new()
         .merge(crear_rutas())
@@ -38,7 +70,10 @@ async fn main() {
         .await
         .expect("Error al crear listener");
     
-    axum::serve(listener, app)
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
         .await
         .expect("Error al ejecutar servidor");
 }
@@ -0,0 +1,136 @@
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use jsonwebtoken::errors::ErrorKind;
+
+use crate::{
+    db::mongo::DbState,
+    models::usuario::Rol,
+    utils::{
+        jwt::{verificar_token, Claims},
+        revocacion::esta_revocado,
+        tokens_personales::{self, Autenticacion},
+    },
+};
+
+// Errores de autenticación devueltos con cuerpo JSON (401, 403 o 500)
+#[derive(Debug)]
+pub enum AuthError {
+    Faltante,
+    Malformado,
+    Invalido,
+    Expirado,
+    Revocado,
+    Prohibido,
+    Interno,
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            AuthError::Faltante => (StatusCode::UNAUTHORIZED, "Token de autenticación no proporcionado"),
+            AuthError::Malformado => (
+                StatusCode::UNAUTHORIZED,
+                "Formato de autorización inválido, se espera 'Bearer <token>'",
+            ),
+            AuthError::Invalido => (StatusCode::UNAUTHORIZED, "Token inválido"),
+            AuthError::Expirado => (StatusCode::UNAUTHORIZED, "El token ha expirado"),
+            AuthError::Revocado => (StatusCode::UNAUTHORIZED, "El token ha sido revocado"),
+            AuthError::Prohibido => (StatusCode::FORBIDDEN, "No tienes permisos para esta acción"),
+            AuthError::Interno => (StatusCode::INTERNAL_SERVER_ERROR, "Error interno del servidor"),
+        };
+
+        (
+            status,
+            Json(serde_json::json!({
+                "success": false,
+                "message": message
+            })),
+        )
+            .into_response()
+    }
+}
+
+// Extractor: lee `Authorization: Bearer <token>`, descarta tokens revocados
+// y entrega los Claims al handler. También admite tokens de acceso personal
+// en las rutas que cubren sus scopes
+#[async_trait]
+impl<S> FromRequestParts<S> for Claims
+where
+    DbState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let header = parts
+            .headers
+            .get(AUTHORIZATION)
+            .ok_or(AuthError::Faltante)?
+            .to_str()
+            .map_err(|_| AuthError::Malformado)?;
+
+        let token = header
+            .strip_prefix("Bearer ")
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .ok_or(AuthError::Malformado)?;
+
+        let db = DbState::from_ref(state);
+
+        if token.starts_with(tokens_personales::PREFIJO) {
+            let scope = tokens_personales::scope_requerido(&parts.method, parts.uri.path())
+                .ok_or(AuthError::Prohibido)?;
+
+            return match tokens_personales::autenticar(&db, token).await {
+                Ok(Autenticacion::Valido(claims)) if claims.permite(scope) => Ok(claims),
+                Ok(Autenticacion::Valido(_)) => Err(AuthError::Prohibido),
+                Ok(Autenticacion::Invalido) => Err(AuthError::Invalido),
+                Ok(Autenticacion::Expirado) => Err(AuthError::Expirado),
+                Err(_) => Err(AuthError::Interno),
+            };
+        }
+
+        let claims = verificar_token(token).map_err(|e| match e.kind() {
+            ErrorKind::ExpiredSignature => AuthError::Expirado,
+            _ => AuthError::Invalido,
+        })?;
+
+        match esta_revocado(&db, &claims).await {
+            Ok(false) => Ok(claims),
+            Ok(true) => Err(AuthError::Revocado),
+            Err(_) => Err(AuthError::Interno),
+        }
+    }
+}
+
+// Guard reutilizable: exige que el token incluya el rol indicado
+pub fn exigir_rol(claims: &Claims, rol: Rol) -> Result<(), AuthError> {
+    if claims.tiene_rol(rol) {
+        Ok(())
+    } else {
+        Err(AuthError::Prohibido)
+    }
+}
+
+// Extractor para handlers exclusivos de administradores
+pub struct Admin(pub Claims);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Admin
+where
+    DbState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request_parts(parts, state).await?;
+        exigir_rol(&claims, Rol::Admin)?;
+        Ok(Admin(claims))
+    }
+}
@@ -0,0 +1,74 @@
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+use std::{convert::Infallible, net::SocketAddr, sync::OnceLock};
+
+use crate::utils::entorno::variable;
+
+// Datos del cliente que hace la petición (dispositivo e IP)
+#[derive(Debug, Clone)]
+pub struct InfoCliente {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+static CONFIAR_EN_PROXY: OnceLock<bool> = OnceLock::new();
+
+// Solo se confía en X-Forwarded-For si la API está detrás de un proxy (TRUST_PROXY=true)
+pub fn inicializar_desde_entorno() -> Result<(), String> {
+    let confiar = match variable("TRUST_PROXY") {
+        None => false,
+        Some(v) if v.eq_ignore_ascii_case("true") => true,
+        Some(v) if v.eq_ignore_ascii_case("false") => false,
+        Some(v) => return Err(format!("TRUST_PROXY inválido: '{}' (true o false)", v)),
+    };
+
+    CONFIAR_EN_PROXY
+        .set(confiar)
+        .map_err(|_| "La confianza en el proxy ya estaba inicializada".to_string())
+}
+
+fn confiar_en_proxy() -> bool {
+    *CONFIAR_EN_PROXY
+        .get()
+        .expect("Confianza en el proxy no inicializada")
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for InfoCliente
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.chars().take(256).collect());
+
+        let ip_proxy = if confiar_en_proxy() {
+            parts
+                .headers
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        } else {
+            None
+        };
+
+        let ip = ip_proxy.or_else(|| {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string())
+        });
+
+        Ok(InfoCliente { user_agent, ip })
+    }
+}
@@ -0,0 +1,60 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EstadoExportacion {
+    Pendiente,
+    Completada,
+    Fallida,
+}
+
+// Exportación de datos personales; el archivo JSON se genera en segundo plano
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Exportacion {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub usuario_id: ObjectId,
+    pub estado: EstadoExportacion,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archivo: Option<String>, // Nombre del archivo dentro de EXPORT_DIR
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "bson::serde_helpers::chrono_datetime_as_bson_datetime_optional"
+    )]
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportacionPublica {
+    pub id: String,
+    pub status: EstadoExportacion,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+}
+
+impl Exportacion {
+    pub fn to_publica(&self) -> ExportacionPublica {
+        let id = self.id.as_ref().map(|id| id.to_hex()).unwrap_or_default();
+        ExportacionPublica {
+            download_url: (self.estado == EstadoExportacion::Completada)
+                .then(|| format!("/auth/me/exports/{}/download", id)),
+            id,
+            status: self.estado,
+            created_at: self.created_at,
+            completed_at: self.completed_at,
+            expires_at: self.expires_at,
+        }
+    }
+}
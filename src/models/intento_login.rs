@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// Contador de logins fallidos por cuenta ("email:<email>") o por IP ("ip:<ip>")
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IntentoLogin {
+    pub clave: String,
+    pub fallos: i32,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "bson::serde_helpers::chrono_datetime_as_bson_datetime_optional"
+    )]
+    pub bloqueado_hasta: Option<DateTime<Utc>>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+}
@@ -1,2 +1,9 @@
 pub mod tarea;
 pub mod usuario;
+pub mod refresh_token;
+pub mod revocacion;
+pub mod sesion;
+pub mod intento_login;
+pub mod oidc;
+pub mod token_personal;
+pub mod exportacion;
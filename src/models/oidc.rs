@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// Estado de un login OIDC en curso, creado en /start y consumido en el callback
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EstadoOidc {
+    pub state: String,
+    pub proveedor: String,
+    pub code_verifier: String, // PKCE
+    pub nonce: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+}
+
+// Cuenta de un proveedor externo vinculada a un usuario
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IdentidadExterna {
+    pub proveedor: String,
+    pub sub: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackOidcQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
@@ -0,0 +1,44 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// Refresh token opaco; solo se guarda su hash SHA-256
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefreshToken {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub usuario_id: ObjectId,
+    pub familia: String, // Todos los tokens rotados desde un mismo login
+    pub token_hash: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+    pub usado: bool,
+    pub revocado: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+impl RefreshToken {
+    pub fn nuevo(
+        usuario_id: ObjectId,
+        familia: String,
+        token_hash: String,
+        expires_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: None,
+            usuario_id,
+            familia,
+            token_hash,
+            created_at: Utc::now(),
+            expires_at,
+            usado: false,
+            revocado: false,
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// Entrada del almacén de revocaciones: todos los tokens de una sesión (`sid`)
+// o todos los del usuario emitidos hasta `emitidos_hasta_ms`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Revocacion {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub usuario_id: ObjectId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emitidos_hasta_ms: Option<i64>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+}
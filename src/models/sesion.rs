@@ -0,0 +1,45 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// Sesión iniciada desde un dispositivo; `sid` identifica la familia de refresh tokens
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Sesion {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub usuario_id: ObjectId,
+    pub sid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub last_seen_at: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SesionPublica {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub actual: bool, // Sesión del token usado en la petición
+}
+
+impl Sesion {
+    pub fn to_publica(&self, sid_actual: &str) -> SesionPublica {
+        SesionPublica {
+            id: self.id.as_ref().map(|id| id.to_hex()).unwrap_or_default(),
+            user_agent: self.user_agent.clone(),
+            ip: self.ip.clone(),
+            created_at: self.created_at,
+            last_seen_at: self.last_seen_at,
+            actual: self.sid == sid_actual,
+        }
+    }
+}
@@ -6,9 +6,55 @@ use serde::{Deserialize, Serialize};
 pub struct Tarea {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
+    pub usuario_id: ObjectId, // Propietario (Claims.sub)
+    pub descripcion: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub fecha_creacion: DateTime<Utc>, // Fecha BSON para poder filtrar y ordenar por rangos
+    pub estado: EstadoTarea,
+    #[serde(default)]
+    pub prioridad: Prioridad, // Media en tareas anteriores a las prioridades
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vencimiento: Option<Vencimiento>,
+    #[serde(default)]
+    pub idioma: Idioma, // Idioma de la descripción para el índice de texto
+}
+
+// Fecha de vencimiento. Las de día completo guardan el día y su zona horaria
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Vencimiento {
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub fecha: DateTime<Utc>, // Instante exacto, o inicio del día en su zona
+    #[serde(default)]
+    pub todo_el_dia: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dia: Option<String>, // AAAA-MM-DD, solo en las de día completo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zona_horaria: Option<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub limite: DateTime<Utc>, // A partir de aquí la tarea está vencida
+}
+
+#[derive(Debug, Serialize)]
+pub struct VencimientoPublico {
+    pub fecha: DateTime<Utc>,
+    pub todo_el_dia: bool,
+    pub dia: Option<String>,
+    pub zona_horaria: Option<String>,
+    pub vencida: bool,
+}
+
+// Tarea tal como se devuelve en la API (fechas en formato ISO 8601)
+#[derive(Debug, Serialize)]
+pub struct TareaPublica {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub usuario_id: ObjectId,
     pub descripcion: String,
     pub fecha_creacion: DateTime<Utc>,
     pub estado: EstadoTarea,
+    pub prioridad: Prioridad,
+    pub vencimiento: Option<VencimientoPublico>,
+    pub idioma: Idioma,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -18,17 +64,191 @@ pub enum EstadoTarea {
     Realizada,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum Prioridad {
+    Baja,
+    #[default]
+    Media,
+    Alta,
+    Urgente,
+}
+
+impl Prioridad {
+    pub const TODAS: [Prioridad; 4] = [
+        Prioridad::Baja,
+        Prioridad::Media,
+        Prioridad::Alta,
+        Prioridad::Urgente,
+    ];
+
+    // Peso para ordenar: mayor cuanto más urgente
+    pub fn peso(self) -> i32 {
+        match self {
+            Prioridad::Baja => 0,
+            Prioridad::Media => 1,
+            Prioridad::Alta => 2,
+            Prioridad::Urgente => 3,
+        }
+    }
+}
+
+// Idiomas de la búsqueda de texto. Se guardan con el código que entiende MongoDB
+// (`language_override` del índice), que aplica las raíces y palabras vacías de cada uno
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Idioma {
+    #[default]
+    Es,
+    En,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CrearTareaRequest {
     pub descripcion: String,
     #[serde(default = "default_estado")]
     pub estado: EstadoTarea,
+    #[serde(default)]
+    pub prioridad: Prioridad,
+    pub fecha_vencimiento: Option<String>, // RFC 3339, AAAA-MM-DDTHH:MM o AAAA-MM-DD (día completo)
+    pub zona_horaria: Option<String>,      // IANA; se aplica a fechas sin desplazamiento (UTC por defecto)
+    pub idioma: Option<Idioma>,            // Español si falta
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ActualizarTareaRequest {
     pub descripcion: Option<String>,
     pub estado: Option<EstadoTarea>,
+    pub prioridad: Option<Prioridad>,
+    // Ausente: no cambia; null: quita la fecha de vencimiento
+    #[serde(default, deserialize_with = "campo_anulable")]
+    pub fecha_vencimiento: Option<Option<String>>,
+    pub zona_horaria: Option<String>,
+    pub idioma: Option<Idioma>,
+}
+
+// Distingue un campo ausente (None) de uno enviado como null (Some(None))
+fn campo_anulable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+// Vistas por fecha de vencimiento de GET /tareas
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Vista {
+    Hoy,
+    Semana,
+    Vencidas,
+    SinFecha,
+}
+
+// Campos por los que se puede ordenar el listado
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CampoOrden {
+    FechaCreacion,
+    FechaVencimiento,
+    Descripcion,
+    Estado,
+    Prioridad, // Prioridad, luego vencimiento más próximo y después las más recientes
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direccion {
+    Asc,
+    Desc,
+}
+
+// Parámetros de GET /tareas; también se serializan para construir los enlaces next/prev
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ListarTareasQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estado: Option<EstadoTarea>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prioridad: Option<Prioridad>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub desde: Option<String>, // RFC 3339 o AAAA-MM-DD (inclusive)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hasta: Option<String>, // RFC 3339 o AAAA-MM-DD (inclusive, el día completo)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vista: Option<Vista>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tz: Option<String>, // Zona IANA con la que se calculan "hoy" y "esta semana"
+    #[serde(default = "default_campo_orden")]
+    pub sort: CampoOrden,
+    #[serde(default = "default_direccion")]
+    pub order: Direccion,
+    #[serde(default = "default_limit")]
+    pub limit: u64,
+    #[serde(default, skip_serializing_if = "es_cero")]
+    pub offset: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>, // Paginación por cursor (solo con sort=fecha_creacion)
+}
+
+// Página de resultados con el total y los enlaces a la página siguiente/anterior
+#[derive(Debug, Serialize)]
+pub struct PaginaTareas {
+    pub data: Vec<TareaPublica>,
+    pub total: Option<u64>, // null en las páginas pedidas con cursor
+    pub limit: u64,
+    pub offset: u64,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+    pub next_cursor: Option<String>, // Solo al ordenar por fecha_creacion
+}
+
+// Parámetros de GET /tareas/search; también se serializan para los enlaces next/prev
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BuscarTareasQuery {
+    pub q: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estado: Option<EstadoTarea>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idioma: Option<Idioma>, // Idioma de la consulta; español si falta
+    #[serde(default = "default_limit")]
+    pub limit: u64,
+    #[serde(default, skip_serializing_if = "es_cero")]
+    pub offset: u64,
+}
+
+// Resultado de búsqueda: la tarea, su relevancia y un fragmento con las coincidencias
+#[derive(Debug, Serialize)]
+pub struct ResultadoBusqueda {
+    #[serde(flatten)]
+    pub tarea: TareaPublica,
+    pub score: f64,
+    pub snippet: String, // HTML escapado con las palabras encontradas entre <mark>
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResultadosBusqueda {
+    pub data: Vec<ResultadoBusqueda>,
+    pub total: u64,
+    pub limit: u64,
+    pub offset: u64,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+fn default_campo_orden() -> CampoOrden {
+    CampoOrden::FechaCreacion
+}
+
+fn default_direccion() -> Direccion {
+    Direccion::Desc
+}
+
+fn default_limit() -> u64 {
+    20
+}
+
+fn es_cero(valor: &u64) -> bool {
+    *valor == 0
 }
 
 fn default_estado() -> EstadoTarea {
@@ -36,12 +256,42 @@ fn default_estado() -> EstadoTarea {
 }
 
 impl Tarea {
-    pub fn nueva(descripcion: String, estado: EstadoTarea) -> Self {
+    pub fn nueva(
+        usuario_id: ObjectId,
+        descripcion: String,
+        estado: EstadoTarea,
+        prioridad: Prioridad,
+        idioma: Idioma,
+    ) -> Self {
         Self {
             id: None,
+            usuario_id,
             descripcion,
             fecha_creacion: Utc::now(),
             estado,
+            prioridad,
+            vencimiento: None,
+            idioma,
+        }
+    }
+
+    pub fn to_publica(&self) -> TareaPublica {
+        TareaPublica {
+            id: self.id,
+            usuario_id: self.usuario_id,
+            descripcion: self.descripcion.clone(),
+            fecha_creacion: self.fecha_creacion,
+            estado: self.estado.clone(),
+            prioridad: self.prioridad,
+            vencimiento: self.vencimiento.as_ref().map(|v| VencimientoPublico {
+                fecha: v.fecha,
+                todo_el_dia: v.todo_el_dia,
+                dia: v.dia.clone(),
+                zona_horaria: v.zona_horaria.clone(),
+                // Vencida cuando pasa su límite sin haberse realizado
+                vencida: !matches!(self.estado, EstadoTarea::Realizada) && v.limite <= Utc::now(),
+            }),
+            idioma: self.idioma,
         }
     }
 }
@@ -0,0 +1,71 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+// Permisos que puede conceder un token de acceso personal
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    #[serde(rename = "tareas:read")]
+    TareasLectura,
+    #[serde(rename = "tareas:write")]
+    TareasEscritura,
+}
+
+// Token de acceso personal para scripts y CI; solo se guarda su hash SHA-256
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TokenPersonal {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub usuario_id: ObjectId,
+    pub nombre: String,
+    pub token_hash: String,
+    pub scopes: Vec<Scope>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "bson::serde_helpers::chrono_datetime_as_bson_datetime_optional"
+    )]
+    pub expires_at: Option<DateTime<Utc>>, // Sin fecha: no caduca
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "bson::serde_helpers::chrono_datetime_as_bson_datetime_optional"
+    )]
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CrearTokenPersonalRequest {
+    #[validate(length(min = 1, max = 100, message = "El nombre debe tener entre 1 y 100 caracteres"))]
+    pub name: String,
+    #[validate(length(min = 1, message = "Indica al menos un scope"))]
+    pub scopes: Vec<Scope>,
+    #[validate(range(min = 1, max = 3650, message = "La caducidad debe estar entre 1 y 3650 días"))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenPersonalPublico {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl TokenPersonal {
+    pub fn to_publico(&self) -> TokenPersonalPublico {
+        TokenPersonalPublico {
+            id: self.id.as_ref().map(|id| id.to_hex()).unwrap_or_default(),
+            name: self.nombre.clone(),
+            scopes: self.scopes.clone(),
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+            last_used_at: self.last_used_at,
+        }
+    }
+}
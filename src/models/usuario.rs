@@ -3,28 +3,66 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
+use crate::models::oidc::IdentidadExterna;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Usuario {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
     pub name: String,
     pub email: String,
-    pub password: String, // Hasheado con bcrypt
+    pub password: String, // Hash Argon2id (o bcrypt en cuentas aún sin migrar)
+    #[serde(default)]
+    pub sin_password: bool, // Creada por OIDC: `password` es aleatoria y nadie la conoce
     pub created_at: DateTime<Utc>,
+    #[serde(default = "roles_por_defecto")]
+    pub roles: Vec<Rol>,
+    #[serde(default)]
+    pub deshabilitado: bool,
+    #[serde(default)]
+    pub email_verified: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_pendiente: Option<String>, // Email nuevo a la espera de verificarse
+    #[serde(default)]
+    pub totp_habilitado: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp_secret: Option<String>, // Base32
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp_secret_pendiente: Option<String>, // Generado pero aún sin confirmar
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp_ultimo_paso: Option<i64>, // Último paso TOTP aceptado (anti-reutilización)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub codigos_recuperacion: Vec<String>, // Hashes SHA-256
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identidades: Vec<IdentidadExterna>, // Cuentas OIDC vinculadas
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub reset_token: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_token_hash: Option<String>, // SHA-256 del token de reseteo
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "bson::serde_helpers::chrono_datetime_as_bson_datetime_optional"
+    )]
     pub reset_token_expire: Option<DateTime<Utc>>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Rol {
+    User,
+    Admin,
+}
+
+fn roles_por_defecto() -> Vec<Rol> {
+    vec![Rol::User]
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct RegistroRequest {
     #[validate(length(min = 2, message = "El nombre debe tener al menos 2 caracteres"))]
     pub name: String,
     #[validate(email(message = "Email inválido"))]
     pub email: String,
-    #[validate(length(min = 6, message = "La contraseña debe tener al menos 6 caracteres"))]
-    pub password: String,
+    pub password: String, // Se comprueba con utils::politica_password
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -40,11 +78,52 @@ pub struct ForgotPasswordRequest {
     pub email: String,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReenviarVerificacionRequest {
+    #[validate(email(message = "Email inválido"))]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CodigoDosFactoresRequest {
+    pub code: String, // Código TOTP o de recuperación
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerificarDosFactoresRequest {
+    pub challenge_token: String,
+    pub code: String, // Código TOTP o de recuperación
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerificarEmailQuery {
+    pub token: String,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct ResetPasswordRequest {
     pub token: String,
-    #[validate(length(min = 6, message = "La contraseña debe tener al menos 6 caracteres"))]
-    pub new_password: String,
+    pub new_password: String, // Se comprueba con utils::politica_password
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ActualizarPerfilRequest {
+    #[validate(length(min = 2, message = "El nombre debe tener al menos 2 caracteres"))]
+    pub name: Option<String>,
+    #[validate(email(message = "Email inválido"))]
+    pub email: Option<String>,
+    pub current_password: Option<String>, // Obligatoria para cambiar el email
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CambiarPasswordRequest {
+    pub current_password: Option<String>, // No se pide en cuentas sin contraseña propia
+    pub new_password: String, // Se comprueba con utils::politica_password
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EliminarCuentaRequest {
+    pub password: Option<String>, // Confirmación de la contraseña actual
 }
 
 #[derive(Debug, Serialize)]
@@ -53,6 +132,8 @@ pub struct AuthResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub token: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<UsuarioPublico>,
     pub message: String,
 }
@@ -62,6 +143,36 @@ pub struct UsuarioPublico {
     pub id: String,
     pub name: String,
     pub email: String,
+    pub email_verified: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_pendiente: Option<String>,
+    #[serde(default)]
+    pub sin_password: bool,
+    pub totp_habilitado: bool,
+    pub roles: Vec<Rol>,
+}
+
+// Vista de un usuario para administradores
+#[derive(Debug, Serialize)]
+pub struct UsuarioAdmin {
+    #[serde(flatten)]
+    pub usuario: UsuarioPublico,
+    pub deshabilitado: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+// Datos del usuario incluidos en la exportación: todo salvo contraseña, secretos y tokens
+#[derive(Debug, Serialize)]
+pub struct UsuarioExportado {
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub roles: Vec<Rol>,
+    pub deshabilitado: bool,
+    pub totp_habilitado: bool,
+    pub identidades: Vec<IdentidadExterna>,
+    pub created_at: DateTime<Utc>,
 }
 
 impl Usuario {
@@ -71,8 +182,19 @@ impl Usuario {
             name,
             email: email.to_lowercase(),
             password: password_hash,
+            sin_password: false,
             created_at: Utc::now(),
-            reset_token: None,
+            roles: roles_por_defecto(),
+            deshabilitado: false,
+            email_verified: false,
+            email_pendiente: None,
+            totp_habilitado: false,
+            totp_secret: None,
+            totp_secret_pendiente: None,
+            totp_ultimo_paso: None,
+            codigos_recuperacion: Vec::new(),
+            identidades: Vec::new(),
+            reset_token_hash: None,
             reset_token_expire: None,
         }
     }
@@ -82,6 +204,33 @@ impl Usuario {
             id: self.id.as_ref().map(|id| id.to_hex()).unwrap_or_default(),
             name: self.name.clone(),
             email: self.email.clone(),
+            email_verified: self.email_verified,
+            email_pendiente: self.email_pendiente.clone(),
+            sin_password: self.sin_password,
+            totp_habilitado: self.totp_habilitado,
+            roles: self.roles.clone(),
+        }
+    }
+
+    pub fn to_admin(&self) -> UsuarioAdmin {
+        UsuarioAdmin {
+            usuario: self.to_publico(),
+            deshabilitado: self.deshabilitado,
+            created_at: self.created_at,
+        }
+    }
+
+    pub fn to_exportado(&self) -> UsuarioExportado {
+        UsuarioExportado {
+            id: self.id.as_ref().map(|id| id.to_hex()).unwrap_or_default(),
+            name: self.name.clone(),
+            email: self.email.clone(),
+            email_verified: self.email_verified,
+            roles: self.roles.clone(),
+            deshabilitado: self.deshabilitado,
+            totp_habilitado: self.totp_habilitado,
+            identidades: self.identidades.clone(),
+            created_at: self.created_at,
         }
     }
 }
@@ -1,15 +1,30 @@
 use axum::{
-    routing::{delete, get, post, put},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 
 use crate::{
     db::mongo::DbState,
     handlers::tareas::{
-        actualizar_tarea, crear_tarea, eliminar_tarea, listar_tareas, obtener_tarea,
+        actualizar_tarea, buscar_tareas, crear_tarea, eliminar_tarea, listar_tareas,
+        obtener_tarea,
     },
     handlers::auth::{
-        registro, login, forgot_password, reset_password,
+        registro, login, refresh, logout, logout_all, verificar_email, reenviar_verificacion,
+        forgot_password, reset_password, jwks,
+    },
+    handlers::sesiones::{eliminar_sesion, listar_sesiones},
+    handlers::perfil::{actualizar_perfil, cambiar_password, eliminar_cuenta, obtener_perfil},
+    handlers::exportaciones::{
+        descargar_exportacion, estado_exportacion, solicitar_exportacion,
+    },
+    handlers::tokens_personales::{crear_token, eliminar_token, listar_tokens},
+    handlers::oidc::{callback_oidc, iniciar_login_oidc},
+    handlers::dos_factores::{
+        confirmar_configuracion, desactivar, iniciar_configuracion, regenerar_codigos, verificar,
+    },
+    handlers::admin::{
+        deshabilitar_usuario, habilitar_usuario, listar_tareas_usuario, listar_usuarios,
     },
 };
 
@@ -18,12 +33,43 @@ pub fn crear_rutas() -> Router<DbState> {
         // Rutas de tareas
         .route("/tareas", post(crear_tarea))
         .route("/tareas", get(listar_tareas))
+        .route("/tareas/search", get(buscar_tareas))
         .route("/tareas/:id", get(obtener_tarea))
         .route("/tareas/:id", put(actualizar_tarea))
         .route("/tareas/:id", delete(eliminar_tarea))
         // Rutas de autenticación
         .route("/auth/register", post(registro))
         .route("/auth/login", post(login))
+        .route("/auth/refresh", post(refresh))
+        .route("/auth/logout", post(logout))
+        .route("/auth/logout-all", post(logout_all))
+        .route("/auth/me", get(obtener_perfil))
+        .route("/auth/me", patch(actualizar_perfil))
+        .route("/auth/me", delete(eliminar_cuenta))
+        .route("/auth/me/exports", post(solicitar_exportacion))
+        .route("/auth/me/exports/:id", get(estado_exportacion))
+        .route("/auth/me/exports/:id/download", get(descargar_exportacion))
+        .route("/auth/change-password", post(cambiar_password))
+        .route("/auth/sessions", get(listar_sesiones))
+        .route("/auth/sessions/:id", delete(eliminar_sesion))
+        .route("/auth/tokens", get(listar_tokens))
+        .route("/auth/tokens", post(crear_token))
+        .route("/auth/tokens/:id", delete(eliminar_token))
+        .route("/auth/verify-email", get(verificar_email))
+        .route("/auth/resend-verification", post(reenviar_verificacion))
         .route("/auth/forgot-password", post(forgot_password))
         .route("/auth/reset-password", post(reset_password))
+        .route("/auth/2fa/setup", post(iniciar_configuracion))
+        .route("/auth/2fa/confirm", post(confirmar_configuracion))
+        .route("/auth/2fa/disable", post(desactivar))
+        .route("/auth/2fa/recovery-codes", post(regenerar_codigos))
+        .route("/auth/2fa/verify", post(verificar))
+        .route("/auth/oidc/:provider/start", get(iniciar_login_oidc))
+        .route("/auth/oidc/:provider/callback", get(callback_oidc))
+        .route("/.well-known/jwks.json", get(jwks))
+        // Rutas de administración (rol admin)
+        .route("/admin/usuarios", get(listar_usuarios))
+        .route("/admin/usuarios/:id/deshabilitar", post(deshabilitar_usuario))
+        .route("/admin/usuarios/:id/habilitar", post(habilitar_usuario))
+        .route("/admin/usuarios/:id/tareas", get(listar_tareas_usuario))
 }
@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+use std::ops::Range;
+
+// Longitud aproximada (en caracteres) de los fragmentos de resultado
+const LONGITUD_FRAGMENTO: usize = 160;
+
+// Longitud mínima de la parte común para resaltar variantes de una palabra
+const RAIZ_MINIMA: usize = 4;
+
+// Minúsculas y sin tildes ni diéresis, como compara el índice de texto de MongoDB
+pub fn normalizar(texto: &str) -> String {
+    texto.chars().flat_map(char::to_lowercase).map(quitar_diacritico).collect()
+}
+
+fn quitar_diacritico(c: char) -> char {
+    match c {
+        'á' | 'à' | 'â' | 'ä' | 'ã' => 'a',
+        'é' | 'è' | 'ê' | 'ë' => 'e',
+        'í' | 'ì' | 'î' | 'ï' => 'i',
+        'ó' | 'ò' | 'ô' | 'ö' | 'õ' => 'o',
+        'ú' | 'ù' | 'û' | 'ü' => 'u',
+        'ñ' => 'n',
+        'ç' => 'c',
+        otro => otro,
+    }
+}
+
+// Palabras a resaltar de una consulta con la sintaxis de $text: se ignoran las
+// exclusiones (-palabra, -"frase") y las comillas de las frases
+pub fn terminos(consulta: &str) -> HashSet<String> {
+    let mut terminos = HashSet::new();
+    let mut anterior = "";
+
+    // Las partes impares son el interior de una frase entre comillas
+    for (i, parte) in consulta.split('"').enumerate() {
+        let palabras: Vec<&str> = if i % 2 == 1 {
+            if anterior.ends_with('-') {
+                vec![]
+            } else {
+                vec![parte]
+            }
+        } else {
+            parte
+                .split_whitespace()
+                .filter(|p| !p.starts_with('-'))
+                .collect()
+        };
+
+        for palabra in palabras.iter().flat_map(|p| p.split(|c: char| !c.is_alphanumeric())) {
+            if !palabra.is_empty() {
+                terminos.insert(normalizar(palabra));
+            }
+        }
+        anterior = parte;
+    }
+    terminos
+}
+
+// El índice compara raíces ("tarea" encuentra "tareas"): se resaltan las palabras iguales
+// al término o que empiezan por él (o él por ellas) con una parte común suficiente
+fn coincide(palabra: &str, terminos: &HashSet<String>) -> bool {
+    terminos.iter().any(|termino| {
+        let (corta, larga) = if palabra.len() <= termino.len() {
+            (palabra, termino.as_str())
+        } else {
+            (termino.as_str(), palabra)
+        };
+        corta == larga || (corta.chars().count() >= RAIZ_MINIMA && larga.starts_with(corta))
+    })
+}
+
+// Rangos (en bytes) de las palabras del texto que coinciden con algún término
+fn coincidencias(texto: &str, terminos: &HashSet<String>) -> Vec<Range<usize>> {
+    let mut rangos = Vec::new();
+    let mut inicio = None;
+
+    for (i, c) in texto.char_indices().chain(std::iter::once((texto.len(), ' '))) {
+        match (c.is_alphanumeric(), inicio) {
+            (true, None) => inicio = Some(i),
+            (false, Some(desde)) => {
+                if coincide(&normalizar(&texto[desde..i]), terminos) {
+                    rangos.push(desde..i);
+                }
+                inicio = None;
+            }
+            _ => {}
+        }
+    }
+    rangos
+}
+
+fn escapar_html(texto: &str) -> String {
+    texto
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// Fragmento del texto alrededor de la primera coincidencia, con las palabras
+// encontradas entre <mark>. El resto del texto se escapa para poder insertarlo como HTML
+pub fn fragmento(texto: &str, terminos: &HashSet<String>) -> String {
+    let rangos = coincidencias(texto, terminos);
+
+    // Ventana de caracteres centrada (aprox.) en la primera coincidencia
+    let limites: Vec<usize> = texto
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(texto.len()))
+        .collect();
+    let total = limites.len() - 1;
+    let (mut desde, mut hasta) = (0, total);
+    if total > LONGITUD_FRAGMENTO {
+        let primera = rangos
+            .first()
+            .map(|r| limites.iter().position(|i| *i == r.start).unwrap_or(0))
+            .unwrap_or(0);
+        desde = primera.saturating_sub(LONGITUD_FRAGMENTO / 4);
+        hasta = (desde + LONGITUD_FRAGMENTO).min(total);
+        desde = hasta.saturating_sub(LONGITUD_FRAGMENTO);
+    }
+    let (inicio, fin) = (limites[desde], limites[hasta]);
+
+    let mut salida = String::new();
+    if inicio > 0 {
+        salida.push('…');
+    }
+    let mut posicion = inicio;
+    for rango in rangos.iter().filter(|r| r.start >= inicio && r.end <= fin) {
+        salida.push_str(&escapar_html(&texto[posicion..rango.start]));
+        salida.push_str("<mark>");
+        salida.push_str(&escapar_html(&texto[rango.clone()]));
+        salida.push_str("</mark>");
+        posicion = rango.end;
+    }
+    salida.push_str(&escapar_html(&texto[posicion..fin]));
+    if fin < texto.len() {
+        salida.push('…');
+    }
+    salida
+}
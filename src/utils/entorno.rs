@@ -0,0 +1,20 @@
+use std::str::FromStr;
+
+// Valor de una variable de entorno sin espacios; None si no existe o está vacía
+pub fn variable(nombre: &str) -> Option<String> {
+    std::env::var(nombre)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+// Número leído del entorno; si falta se usa el valor por defecto y si no se
+// puede interpretar es un error
+pub fn numero<T: FromStr>(nombre: &str, por_defecto: T) -> Result<T, String> {
+    match variable(nombre) {
+        Some(valor) => valor
+            .parse()
+            .map_err(|_| format!("{} inválido: '{}'", nombre, valor)),
+        None => Ok(por_defecto),
+    }
+}
@@ -0,0 +1,212 @@
+use bson::{oid::ObjectId, Bson, Document};
+use chrono::{Duration, Utc};
+use futures::stream::TryStreamExt;
+use mongodb::{bson::doc, Database};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+use crate::{
+    db::mongo::{
+        obtener_coleccion_exportaciones, obtener_coleccion_sesiones,
+        obtener_coleccion_tokens_personales,
+    },
+    models::{
+        exportacion::{EstadoExportacion, Exportacion},
+        sesion::Sesion,
+        token_personal::{TokenPersonal, TokenPersonalPublico},
+        usuario::Usuario,
+    },
+    utils::entorno::{numero, variable},
+    utils::mailer::{self, Correo},
+};
+
+static CONFIG: OnceLock<ConfigExportacion> = OnceLock::new();
+
+struct ConfigExportacion {
+    directorio: PathBuf,
+    duracion: Duration,
+}
+
+// Configuración de las exportaciones:
+// - EXPORT_DIR: carpeta donde se guardan los archivos, por defecto "exportaciones"; se
+//   crea al arrancar para detectar cuanto antes una ruta no utilizable
+// - EXPORT_TTL_DAYS: días que se conserva cada exportación, por defecto 7
+pub fn inicializar_desde_entorno() -> Result<(), String> {
+    let directorio =
+        PathBuf::from(variable("EXPORT_DIR").unwrap_or_else(|| "exportaciones".to_string()));
+    std::fs::create_dir_all(&directorio)
+        .map_err(|e| format!("No se pudo crear EXPORT_DIR '{}': {}", directorio.display(), e))?;
+
+    let dias = numero("EXPORT_TTL_DAYS", 7)?;
+    let duracion = Duration::try_days(dias)
+        .filter(|_| dias > 0)
+        .ok_or_else(|| "EXPORT_TTL_DAYS debe ser mayor que 0".to_string())?;
+
+    CONFIG
+        .set(ConfigExportacion { directorio, duracion })
+        .map_err(|_| "La configuración de exportaciones ya estaba inicializada".to_string())
+}
+
+fn config() -> &'static ConfigExportacion {
+    CONFIG.get().expect("Configuración de exportaciones no inicializada")
+}
+
+pub fn directorio() -> &'static Path {
+    &config().directorio
+}
+
+pub fn duracion_exportacion() -> Duration {
+    config().duracion
+}
+
+// Borra archivo y registro de las exportaciones que cumplan el filtro
+pub async fn eliminar(db: &Database, filtro: Document) -> Result<(), mongodb::error::Error> {
+    let coleccion = obtener_coleccion_exportaciones(db);
+    let exportaciones: Vec<Exportacion> = coleccion
+        .find(filtro.clone(), None)
+        .await?
+        .try_collect()
+        .await?;
+
+    for exportacion in &exportaciones {
+        if let Some(archivo) = &exportacion.archivo {
+            let _ = tokio::fs::remove_file(directorio().join(archivo)).await;
+        }
+    }
+
+    coleccion.delete_many(filtro, None).await?;
+    Ok(())
+}
+
+// Genera el archivo de la exportación y deja el resultado en su registro
+pub async fn generar(db: Database, exportacion_id: ObjectId, usuario: Usuario) {
+    let coleccion = obtener_coleccion_exportaciones(&db);
+    let archivo = format!("{}.json", exportacion_id.to_hex());
+
+    let resultado = escribir_archivo(&db, &usuario, &archivo).await;
+    let cambios = match &resultado {
+        Ok(()) => doc! {
+            "estado": bson::to_bson(&EstadoExportacion::Completada).unwrap(),
+            "archivo": &archivo,
+            "completed_at": Utc::now(),
+        },
+        Err(e) => {
+            eprintln!("Error al generar la exportación {}: {}", exportacion_id, e);
+            let _ = tokio::fs::remove_file(ruta_temporal(&archivo)).await;
+            doc! {
+                "estado": bson::to_bson(&EstadoExportacion::Fallida).unwrap(),
+                "error": "No se pudo generar la exportación",
+                "completed_at": Utc::now(),
+            }
+        }
+    };
+
+    if let Err(e) = coleccion
+        .update_one(doc! { "_id": exportacion_id }, doc! { "$set": cambios }, None)
+        .await
+    {
+        eprintln!("Error al actualizar la exportación {}: {}", exportacion_id, e);
+        return;
+    }
+    if resultado.is_err() {
+        return;
+    }
+
+    let correo = Correo {
+        para: usuario.email.clone(),
+        asunto: "Tu exportación de datos está lista".to_string(),
+        cuerpo: format!(
+            "Hola {},\n\nLa exportación de tus datos que solicitaste ya está disponible. \
+             Puedes descargarla desde tu perfil en:\n\n{}\n",
+            usuario.name,
+            mailer::url("/perfil")
+        ),
+    };
+    if let Err(e) = mailer::enviar(correo).await {
+        eprintln!("Error al enviar aviso de exportación: {}", e);
+    }
+}
+
+// Archivo donde se escribe la exportación hasta que está completa
+fn ruta_temporal(archivo: &str) -> PathBuf {
+    directorio().join(format!("{}.tmp", archivo))
+}
+
+// Escribe el JSON por partes: las tareas se vuelcan según se leen del cursor
+async fn escribir_archivo(db: &Database, usuario: &Usuario, archivo: &str) -> Result<(), String> {
+    let usuario_id = usuario.id.ok_or("Usuario sin id")?;
+    let directorio = directorio();
+    tokio::fs::create_dir_all(&directorio)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let ruta_temporal = ruta_temporal(archivo);
+    let mut salida = BufWriter::new(
+        tokio::fs::File::create(&ruta_temporal)
+            .await
+            .map_err(|e| e.to_string())?,
+    );
+
+    let cabecera = serde_json::json!({
+        "generated_at": Utc::now(),
+        "usuario": usuario.to_exportado(),
+    })
+    .to_string();
+    // Se reabre el objeto para añadir el resto de secciones
+    escribir(&mut salida, &cabecera[..cabecera.len() - 1]).await?;
+
+    // Documentos crudos: se exporta todo lo guardado aunque no encaje en el modelo actual
+    escribir(&mut salida, ",\"tareas\":[").await?;
+    let mut cursor = db
+        .collection::<Document>("tareas")
+        .find(doc! { "usuario_id": usuario_id }, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut primera = true;
+    while let Some(tarea) = cursor.try_next().await.map_err(|e| e.to_string())? {
+        if !primera {
+            escribir(&mut salida, ",").await?;
+        }
+        primera = false;
+        escribir(&mut salida, &Bson::Document(tarea).into_relaxed_extjson().to_string()).await?;
+    }
+    escribir(&mut salida, "]").await?;
+
+    // Actividad: sesiones abiertas y uso de tokens personales
+    let sesiones: Vec<Sesion> = obtener_coleccion_sesiones(db)
+        .find(doc! { "usuario_id": usuario_id }, None)
+        .await
+        .map_err(|e| e.to_string())?
+        .try_collect()
+        .await
+        .map_err(|e| e.to_string())?;
+    let tokens: Vec<TokenPersonal> = obtener_coleccion_tokens_personales(db)
+        .find(doc! { "usuario_id": usuario_id }, None)
+        .await
+        .map_err(|e| e.to_string())?
+        .try_collect()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let actividad = serde_json::json!({
+        "sesiones": sesiones.iter().map(|s| s.to_publica("")).collect::<Vec<_>>(),
+        "tokens_personales": tokens
+            .iter()
+            .map(|t| t.to_publico())
+            .collect::<Vec<TokenPersonalPublico>>(),
+    });
+    escribir(&mut salida, &format!(",\"actividad\":{}}}", actividad)).await?;
+
+    salida.flush().await.map_err(|e| e.to_string())?;
+    tokio::fs::rename(&ruta_temporal, directorio.join(archivo))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+async fn escribir(salida: &mut BufWriter<tokio::fs::File>, texto: &str) -> Result<(), String> {
+    salida
+        .write_all(texto.as_bytes())
+        .await
+        .map_err(|e| e.to_string())
+}
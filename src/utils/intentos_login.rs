@@ -0,0 +1,190 @@
+use chrono::{DateTime, Duration, Utc};
+use mongodb::{
+    bson::doc,
+    options::{FindOneAndUpdateOptions, ReturnDocument},
+    Database,
+};
+
+use std::sync::OnceLock;
+
+use crate::{db::mongo::obtener_coleccion_intentos_login, utils::entorno::numero};
+
+static LIMITES: OnceLock<Limites> = OnceLock::new();
+
+struct Limites {
+    fallos_cuenta: i32,
+    fallos_ip: i32,
+    minutos_bloqueo: i64,
+}
+
+// Resultado de registrar un login fallido
+pub struct Fallo {
+    pub fallos: i32,
+    pub cuenta_bloqueada: bool, // La cuenta acaba de bloquearse con este intento
+}
+
+fn positivo<T: std::str::FromStr + PartialOrd + Default>(
+    nombre: &str,
+    por_defecto: T,
+) -> Result<T, String> {
+    let valor = numero(nombre, por_defecto)?;
+    if valor <= T::default() {
+        return Err(format!("{} debe ser mayor que 0", nombre));
+    }
+    Ok(valor)
+}
+
+// Límites del bloqueo por fallos:
+// - LOGIN_MAX_ATTEMPTS: fallos permitidos por cuenta, por defecto 5
+// - LOGIN_MAX_ATTEMPTS_IP: fallos permitidos por IP, más alto por IPs compartidas, por defecto 20
+// - LOGIN_LOCKOUT_MINUTES: duración del bloqueo y de la ventana de conteo, por defecto 15
+pub fn inicializar_desde_entorno() -> Result<(), String> {
+    let limites = Limites {
+        fallos_cuenta: positivo("LOGIN_MAX_ATTEMPTS", 5)?,
+        fallos_ip: positivo("LOGIN_MAX_ATTEMPTS_IP", 20)?,
+        minutos_bloqueo: positivo("LOGIN_LOCKOUT_MINUTES", 15)?,
+    };
+
+    LIMITES
+        .set(limites)
+        .map_err(|_| "Los límites de login ya estaban inicializados".to_string())
+}
+
+fn limites() -> &'static Limites {
+    LIMITES.get().expect("Límites de login no inicializados")
+}
+
+fn max_fallos_cuenta() -> i32 {
+    limites().fallos_cuenta
+}
+
+fn max_fallos_ip() -> i32 {
+    limites().fallos_ip
+}
+
+fn duracion_bloqueo() -> Duration {
+    Duration::minutes(limites().minutos_bloqueo)
+}
+
+pub fn clave_cuenta(email: &str) -> String {
+    format!("email:{}", email)
+}
+
+pub fn clave_ip(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+pub fn clave_2fa(usuario_id: &str) -> String {
+    format!("2fa:{}", usuario_id)
+}
+
+// Retraso progresivo ante fallos: 250 ms por fallo, hasta 3 segundos
+pub fn retraso(fallos: i32) -> std::time::Duration {
+    std::time::Duration::from_millis((fallos.max(0) as u64 * 250).min(3000))
+}
+
+// Devuelve el fin del bloqueo más lejano entre las claves indicadas, si hay alguno activo
+pub async fn bloqueo_activo(
+    db: &Database,
+    claves: &[String],
+) -> Result<Option<DateTime<Utc>>, mongodb::error::Error> {
+    let mut hasta: Option<DateTime<Utc>> = None;
+
+    for clave in claves {
+        if let Some(intento) = obtener_coleccion_intentos_login(db)
+            .find_one(doc! { "clave": clave }, None)
+            .await?
+        {
+            if let Some(bloqueado_hasta) = intento.bloqueado_hasta.filter(|h| *h > Utc::now()) {
+                hasta = Some(hasta.map_or(bloqueado_hasta, |h| h.max(bloqueado_hasta)));
+            }
+        }
+    }
+
+    Ok(hasta)
+}
+
+// Suma un fallo a la clave y la bloquea al alcanzar el máximo
+async fn sumar_fallo(
+    db: &Database,
+    clave: &str,
+    maximo: i32,
+) -> Result<(i32, bool), mongodb::error::Error> {
+    let coleccion = obtener_coleccion_intentos_login(db);
+    let ahora = Utc::now();
+    let opciones = FindOneAndUpdateOptions::builder()
+        .upsert(true)
+        .return_document(ReturnDocument::After)
+        .build();
+
+    let intento = coleccion
+        .find_one_and_update(
+            doc! { "clave": clave },
+            doc! {
+                "$inc": { "fallos": 1 },
+                "$set": { "expires_at": ahora + duracion_bloqueo() },
+            },
+            opciones,
+        )
+        .await?;
+
+    let fallos = intento.map(|i| i.fallos).unwrap_or(1);
+    if fallos < maximo {
+        return Ok((fallos, false));
+    }
+
+    // Bloquear y reiniciar el contador para la siguiente ventana
+    let hasta = ahora + duracion_bloqueo();
+    coleccion
+        .update_one(
+            doc! { "clave": clave },
+            doc! {
+                "$set": {
+                    "fallos": 0,
+                    "bloqueado_hasta": hasta,
+                    "expires_at": hasta + duracion_bloqueo(),
+                }
+            },
+            None,
+        )
+        .await?;
+    Ok((fallos, true))
+}
+
+pub async fn registrar_fallo(
+    db: &Database,
+    email: &str,
+    ip: Option<&str>,
+) -> Result<Fallo, mongodb::error::Error> {
+    let (fallos, cuenta_bloqueada) = sumar_fallo(db, &clave_cuenta(email), max_fallos_cuenta()).await?;
+    if let Some(ip) = ip {
+        sumar_fallo(db, &clave_ip(ip), max_fallos_ip()).await?;
+    }
+
+    Ok(Fallo {
+        fallos,
+        cuenta_bloqueada,
+    })
+}
+
+// Código de segundo factor incorrecto; comparte el límite de fallos de la cuenta
+pub async fn registrar_fallo_2fa(
+    db: &Database,
+    usuario_id: &str,
+) -> Result<Fallo, mongodb::error::Error> {
+    let (fallos, cuenta_bloqueada) =
+        sumar_fallo(db, &clave_2fa(usuario_id), max_fallos_cuenta()).await?;
+
+    Ok(Fallo {
+        fallos,
+        cuenta_bloqueada,
+    })
+}
+
+// Un acierto reinicia el contador de la clave (el de la IP caduca solo)
+pub async fn limpiar_fallos(db: &Database, clave: &str) -> Result<(), mongodb::error::Error> {
+    obtener_coleccion_intentos_login(db)
+        .delete_one(doc! { "clave": clave }, None)
+        .await?;
+    Ok(())
+}
@@ -1,38 +1,450 @@
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
-use serde::{Deserialize, Serialize};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    errors::ErrorKind,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use rsa::{pkcs8::DecodePublicKey, traits::PublicKeyParts, RsaPublicKey};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use chrono::{Duration, Utc};
+use std::collections::HashMap;
+use std::sync::OnceLock;
 
-const JWT_SECRET: &str = "tu_clave_secreta_super_segura_cambiar_en_produccion";
+use crate::{
+    models::{token_personal::Scope, usuario::Rol},
+    utils::entorno::variable,
+};
+
+// Secreto usado solo fuera de producción cuando no se configura JWT_SECRET
+const JWT_SECRET_DESARROLLO: &str = "clave_secreta_solo_para_desarrollo";
+// Igual para JWT_INTERNAL_SECRET
+const JWT_INTERNAL_SECRET_DESARROLLO: &str = "clave_interna_solo_para_desarrollo";
+const KID_POR_DEFECTO: &str = "principal";
+// `kid` de los tokens internos; nunca se publica en el JWKS
+const KID_INTERNO: &str = "interno";
+const MINUTOS_ACCESO_POR_DEFECTO: i64 = 15;
+const PROPOSITO_VERIFICAR_EMAIL: &str = "verificar_email";
+const PROPOSITO_DESAFIO_2FA: &str = "2fa";
+
+// Prefijo DER de SubjectPublicKeyInfo para Ed25519 (seguido de 32 bytes de clave)
+const PREFIJO_SPKI_ED25519: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+static CONFIG: OnceLock<ConfigJwt> = OnceLock::new();
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // user_id
     pub exp: usize,  // expiración
+    pub iat: usize,  // emisión
+    pub iat_ms: i64, // emisión en milisegundos (revocación)
+    pub sid: String, // sesión (familia de refresh tokens)
+    #[serde(default)]
+    pub roles: Vec<Rol>,
+    // Solo en tokens de acceso personal; los JWT de sesión no tienen límite de scopes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<Scope>>,
 }
 
-pub fn generar_token(user_id: &str) -> Result<String, jsonwebtoken::errors::Error> {
-    let expiracion = Utc::now()
-        .checked_add_signed(Duration::days(7))
+// Claims del enlace de verificación; `email` invalida el enlace si la dirección cambia
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClaimsVerificacionEmail {
+    pub sub: String,
+    pub email: String,
+    pub exp: usize,
+    pub proposito: String,
+}
+
+// Claims del desafío de login con 2FA: prueba que la contraseña ya se verificó
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClaimsDesafio2fa {
+    pub sub: String,
+    pub exp: usize,
+    pub proposito: String,
+}
+
+impl Claims {
+    pub fn tiene_rol(&self, rol: Rol) -> bool {
+        self.roles.contains(&rol)
+    }
+
+    pub fn permite(&self, scope: Scope) -> bool {
+        self.scopes.as_ref().is_none_or(|scopes| scopes.contains(&scope))
+    }
+}
+
+// Claves de firma y verificación cargadas al arrancar
+struct ConfigJwt {
+    algoritmo: Algorithm,
+    kid_activo: String,
+    duracion_acceso: Duration,
+    clave_firma: EncodingKey,
+    claves_verificacion: HashMap<String, ClaveVerificacion>,
+    jwks: JwkSet,
+    // Clave HS256 solo para tokens de un único propósito (desafío 2FA, verificación de
+    // email), que otros servicios no deben poder aceptar como tokens de acceso
+    clave_interna_firma: EncodingKey,
+    clave_interna_verificacion: DecodingKey,
+}
+
+struct ClaveVerificacion {
+    algoritmo: Algorithm,
+    clave: DecodingKey,
+}
+
+// Carga la configuración JWT desde variables de entorno:
+// - JWT_ALGORITHM: HS256 (por defecto), RS256 o EdDSA
+// - JWT_SECRET: secreto HS256 con el que se firman los tokens nuevos
+// - JWT_PRIVATE_KEY_PATH / JWT_PUBLIC_KEY_PATH: par de claves PEM para RS256/EdDSA
+// - JWT_KID: identificador (`kid`) de la clave activa, por defecto "principal"
+// - JWT_ACCESS_TTL_MINUTES: duración de los tokens de acceso, por defecto 15 minutos
+// - JWT_VERIFICATION_KEYS: secretos HS256 anteriores, formato "kid1:secreto1,kid2:secreto2"
+// - JWT_VERIFICATION_PUBLIC_KEYS: claves públicas anteriores, formato "kid:ALG:ruta.pem,..."
+// - JWT_INTERNAL_SECRET: secreto HS256 de los tokens internos, distinto de JWT_SECRET
+// - APP_ENV: si vale "production", falta de JWT_SECRET o JWT_INTERNAL_SECRET es un error
+pub fn inicializar_desde_entorno() -> Result<(), String> {
+    let produccion = std::env::var("APP_ENV")
+        .map(|v| v.eq_ignore_ascii_case("production"))
+        .unwrap_or(false);
+
+    let algoritmo = match variable("JWT_ALGORITHM").as_deref() {
+        None | Some("HS256") => Algorithm::HS256,
+        Some("RS256") => Algorithm::RS256,
+        Some("EdDSA") => Algorithm::EdDSA,
+        Some(otro) => return Err(format!("JWT_ALGORITHM no soportado: '{}'", otro)),
+    };
+
+    let kid_activo = variable("JWT_KID").unwrap_or_else(|| KID_POR_DEFECTO.to_string());
+
+    let minutos_acceso = match variable("JWT_ACCESS_TTL_MINUTES") {
+        Some(v) => v
+            .parse::<i64>()
+            .ok()
+            .filter(|m| *m > 0)
+            .ok_or_else(|| format!("JWT_ACCESS_TTL_MINUTES inválido: '{}'", v))?,
+        None => MINUTOS_ACCESO_POR_DEFECTO,
+    };
+
+    let mut claves_verificacion = HashMap::new();
+    let mut jwks = JwkSet { keys: Vec::new() };
+    let mut secreto_acceso = None;
+
+    let clave_firma = if algoritmo == Algorithm::HS256 {
+        let secreto = match variable("JWT_SECRET") {
+            Some(s) => s,
+            None if produccion => {
+                return Err("JWT_SECRET es obligatorio cuando APP_ENV=production".to_string())
+            }
+            None => {
+                println!("⚠ JWT_SECRET no configurado, usando secreto de desarrollo");
+                JWT_SECRET_DESARROLLO.to_string()
+            }
+        };
+        claves_verificacion.insert(
+            kid_activo.clone(),
+            ClaveVerificacion {
+                algoritmo,
+                clave: DecodingKey::from_secret(secreto.as_bytes()),
+            },
+        );
+        let clave = EncodingKey::from_secret(secreto.as_bytes());
+        secreto_acceso = Some(secreto);
+        clave
+    } else {
+        let ruta_privada = variable("JWT_PRIVATE_KEY_PATH")
+            .ok_or("JWT_PRIVATE_KEY_PATH es obligatorio para RS256/EdDSA")?;
+        let ruta_publica = variable("JWT_PUBLIC_KEY_PATH")
+            .ok_or("JWT_PUBLIC_KEY_PATH es obligatorio para RS256/EdDSA")?;
+
+        let pem_privado = leer_pem(&ruta_privada)?;
+        let clave_firma = match algoritmo {
+            Algorithm::RS256 => EncodingKey::from_rsa_pem(&pem_privado),
+            _ => EncodingKey::from_ed_pem(&pem_privado),
+        }
+        .map_err(|e| format!("Clave privada inválida en '{}': {}", ruta_privada, e))?;
+
+        let (verificacion, jwk) = cargar_clave_publica(&kid_activo, algoritmo, &ruta_publica)?;
+        claves_verificacion.insert(kid_activo.clone(), verificacion);
+        jwks.keys.push(jwk);
+        clave_firma
+    };
+
+    if let Some(anteriores) = variable("JWT_VERIFICATION_KEYS") {
+        for entrada in anteriores.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (kid, secreto) = entrada
+                .split_once(':')
+                .filter(|(kid, secreto)| !kid.is_empty() && !secreto.is_empty())
+                .ok_or_else(|| format!("Entrada inválida en JWT_VERIFICATION_KEYS: '{}'", entrada))?;
+            if claves_verificacion.contains_key(kid) {
+                return Err(format!("El kid '{}' está duplicado", kid));
+            }
+            claves_verificacion.insert(
+                kid.to_string(),
+                ClaveVerificacion {
+                    algoritmo: Algorithm::HS256,
+                    clave: DecodingKey::from_secret(secreto.as_bytes()),
+                },
+            );
+        }
+    }
+
+    if let Some(anteriores) = variable("JWT_VERIFICATION_PUBLIC_KEYS") {
+        for entrada in anteriores.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let mut partes = entrada.splitn(3, ':');
+            let (kid, alg, ruta) = match (partes.next(), partes.next(), partes.next()) {
+                (Some(kid), Some(alg), Some(ruta)) if !kid.is_empty() && !ruta.is_empty() => {
+                    (kid, alg, ruta)
+                }
+                _ => {
+                    return Err(format!(
+                        "Entrada inválida en JWT_VERIFICATION_PUBLIC_KEYS: '{}'",
+                        entrada
+                    ))
+                }
+            };
+            let algoritmo = match alg {
+                "RS256" => Algorithm::RS256,
+                "EdDSA" => Algorithm::EdDSA,
+                otro => return Err(format!("Algoritmo no soportado para '{}': '{}'", kid, otro)),
+            };
+            if claves_verificacion.contains_key(kid) {
+                return Err(format!("El kid '{}' está duplicado", kid));
+            }
+            let (verificacion, jwk) = cargar_clave_publica(kid, algoritmo, ruta)?;
+            claves_verificacion.insert(kid.to_string(), verificacion);
+            jwks.keys.push(jwk);
+        }
+    }
+
+    if claves_verificacion.contains_key(KID_INTERNO) {
+        return Err(format!("El kid '{}' está reservado", KID_INTERNO));
+    }
+
+    let secreto_interno = match variable("JWT_INTERNAL_SECRET") {
+        Some(s) => s,
+        None if produccion => {
+            return Err("JWT_INTERNAL_SECRET es obligatorio cuando APP_ENV=production".to_string())
+        }
+        None => {
+            println!("⚠ JWT_INTERNAL_SECRET no configurado, usando secreto de desarrollo");
+            JWT_INTERNAL_SECRET_DESARROLLO.to_string()
+        }
+    };
+    if secreto_acceso.as_deref() == Some(secreto_interno.as_str()) {
+        return Err("JWT_INTERNAL_SECRET debe ser distinto de JWT_SECRET".to_string());
+    }
+
+    CONFIG
+        .set(ConfigJwt {
+            algoritmo,
+            kid_activo,
+            duracion_acceso: Duration::minutes(minutos_acceso),
+            clave_firma,
+            claves_verificacion,
+            jwks,
+            clave_interna_firma: EncodingKey::from_secret(secreto_interno.as_bytes()),
+            clave_interna_verificacion: DecodingKey::from_secret(secreto_interno.as_bytes()),
+        })
+        .map_err(|_| "La configuración JWT ya estaba inicializada".to_string())
+}
+
+fn leer_pem(ruta: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(ruta).map_err(|e| format!("No se pudo leer '{}': {}", ruta, e))
+}
+
+// Carga una clave pública PEM y construye su entrada JWKS
+fn cargar_clave_publica(
+    kid: &str,
+    algoritmo: Algorithm,
+    ruta: &str,
+) -> Result<(ClaveVerificacion, Jwk), String> {
+    let pem = leer_pem(ruta)?;
+    let texto = std::str::from_utf8(&pem).map_err(|_| format!("PEM inválido en '{}'", ruta))?;
+    let error_clave = |e: String| format!("Clave pública inválida en '{}': {}", ruta, e);
+
+    let (clave, parametros, algoritmo_jwk) = match algoritmo {
+        Algorithm::RS256 => {
+            let clave = DecodingKey::from_rsa_pem(&pem).map_err(|e| error_clave(e.to_string()))?;
+            let publica = RsaPublicKey::from_public_key_pem(texto)
+                .map_err(|e| error_clave(e.to_string()))?;
+            let parametros = AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(publica.n().to_bytes_be()),
+                e: URL_SAFE_NO_PAD.encode(publica.e().to_bytes_be()),
+            });
+            (clave, parametros, KeyAlgorithm::RS256)
+        }
+        _ => {
+            let clave = DecodingKey::from_ed_pem(&pem).map_err(|e| error_clave(e.to_string()))?;
+            let der = pem::parse(texto).map_err(|e| error_clave(e.to_string()))?;
+            let x = der
+                .contents()
+                .strip_prefix(&PREFIJO_SPKI_ED25519[..])
+                .filter(|x| x.len() == 32)
+                .ok_or_else(|| error_clave("se esperaba una clave pública Ed25519".to_string()))?;
+            let parametros = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(x),
+            });
+            (clave, parametros, KeyAlgorithm::EdDSA)
+        }
+    };
+
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(algoritmo_jwk),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: parametros,
+    };
+
+    Ok((ClaveVerificacion { algoritmo, clave }, jwk))
+}
+
+fn config() -> &'static ConfigJwt {
+    CONFIG
+        .get()
+        .expect("Configuración JWT no inicializada")
+}
+
+// Claves públicas para que otros servicios verifiquen los tokens (vacío con HS256)
+pub fn jwks() -> &'static JwkSet {
+    &config().jwks
+}
+
+// Duración de los tokens de acceso; una revocación nunca necesita durar más
+pub fn duracion_acceso() -> Duration {
+    config().duracion_acceso
+}
+
+// Firma cualquier conjunto de claims con la clave activa
+fn firmar<T: Serialize>(claims: &T) -> Result<String, jsonwebtoken::errors::Error> {
+    let config = config();
+    let mut header = Header::new(config.algoritmo);
+    header.kid = Some(config.kid_activo.clone());
+
+    encode(&header, claims, &config.clave_firma)
+}
+
+fn decodificar<T: DeserializeOwned>(token: &str) -> Result<T, jsonwebtoken::errors::Error> {
+    let config = config();
+
+    // Los tokens sin `kid` (emitidos antes de la rotación) se validan con la clave activa
+    let kid = decode_header(token)?.kid.unwrap_or_else(|| config.kid_activo.clone());
+    let verificacion = config
+        .claves_verificacion
+        .get(&kid)
+        .ok_or_else(|| jsonwebtoken::errors::Error::from(ErrorKind::InvalidToken))?;
+
+    decode::<T>(token, &verificacion.clave, &Validation::new(verificacion.algoritmo))
+        .map(|data| data.claims)
+}
+
+// Firma y verifica tokens internos: solo esta API los acepta
+fn firmar_interno<T: Serialize>(claims: &T) -> Result<String, jsonwebtoken::errors::Error> {
+    let mut header = Header::new(Algorithm::HS256);
+    header.kid = Some(KID_INTERNO.to_string());
+
+    encode(&header, claims, &config().clave_interna_firma)
+}
+
+fn decodificar_interno<T: DeserializeOwned>(token: &str) -> Result<T, jsonwebtoken::errors::Error> {
+    decode::<T>(
+        token,
+        &config().clave_interna_verificacion,
+        &Validation::new(Algorithm::HS256),
+    )
+    .map(|data| data.claims)
+}
+
+pub fn generar_token(
+    user_id: &str,
+    sid: &str,
+    roles: &[Rol],
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let ahora = Utc::now();
+    let expiracion = ahora
+        .checked_add_signed(duracion_acceso())
         .expect("Fecha de expiración válida")
         .timestamp() as usize;
 
     let claims = Claims {
         sub: user_id.to_string(),
         exp: expiracion,
+        iat: ahora.timestamp() as usize,
+        iat_ms: ahora.timestamp_millis(),
+        sid: sid.to_string(),
+        roles: roles.to_vec(),
+        scopes: None,
     };
 
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.as_ref()),
-    )
+    firmar(&claims)
 }
 
 pub fn verificar_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(JWT_SECRET.as_ref()),
-        &Validation::default(),
-    )
-    .map(|data| data.claims)
+    decodificar(token)
+}
+
+// Token firmado para el enlace de verificación de email (válido 24 horas)
+pub fn generar_token_verificacion_email(
+    user_id: &str,
+    email: &str,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let expiracion = Utc::now()
+        .checked_add_signed(Duration::hours(24))
+        .expect("Fecha de expiración válida")
+        .timestamp() as usize;
+
+    firmar_interno(&ClaimsVerificacionEmail {
+        sub: user_id.to_string(),
+        email: email.to_string(),
+        exp: expiracion,
+        proposito: PROPOSITO_VERIFICAR_EMAIL.to_string(),
+    })
+}
+
+pub fn verificar_token_verificacion_email(
+    token: &str,
+) -> Result<ClaimsVerificacionEmail, jsonwebtoken::errors::Error> {
+    decodificar_interno::<ClaimsVerificacionEmail>(token).and_then(|claims| {
+        if claims.proposito == PROPOSITO_VERIFICAR_EMAIL {
+            Ok(claims)
+        } else {
+            Err(ErrorKind::InvalidToken.into())
+        }
+    })
+}
+
+// Token de desafío emitido tras una contraseña correcta cuando la cuenta tiene 2FA (5 minutos)
+pub fn generar_token_desafio_2fa(user_id: &str) -> Result<String, jsonwebtoken::errors::Error> {
+    let expiracion = Utc::now()
+        .checked_add_signed(Duration::minutes(5))
+        .expect("Fecha de expiración válida")
+        .timestamp() as usize;
+
+    firmar_interno(&ClaimsDesafio2fa {
+        sub: user_id.to_string(),
+        exp: expiracion,
+        proposito: PROPOSITO_DESAFIO_2FA.to_string(),
+    })
+}
+
+pub fn verificar_token_desafio_2fa(
+    token: &str,
+) -> Result<ClaimsDesafio2fa, jsonwebtoken::errors::Error> {
+    decodificar_interno::<ClaimsDesafio2fa>(token).and_then(|claims| {
+        if claims.proposito == PROPOSITO_DESAFIO_2FA {
+            Ok(claims)
+        } else {
+            Err(ErrorKind::InvalidToken.into())
+        }
+    })
 }
@@ -0,0 +1,160 @@
+use async_trait::async_trait;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use chrono::Utc;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::utils::entorno::variable;
+
+static MAILER: OnceLock<Box<dyn Mailer>> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub struct Correo {
+    pub para: String,
+    pub asunto: String,
+    pub cuerpo: String,
+}
+
+// Canal de envío de correos; la implementación se elige al arrancar
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn enviar(&self, correo: &Correo) -> Result<(), String>;
+}
+
+// Envío real mediante SMTP
+pub struct MailerSmtp {
+    transporte: AsyncSmtpTransport<Tokio1Executor>,
+    remitente: Mailbox,
+}
+
+#[async_trait]
+impl Mailer for MailerSmtp {
+    async fn enviar(&self, correo: &Correo) -> Result<(), String> {
+        let mensaje = Message::builder()
+            .from(self.remitente.clone())
+            .to(correo.para.parse::<Mailbox>().map_err(|e| e.to_string())?)
+            .subject(&correo.asunto)
+            .header(ContentType::TEXT_PLAIN)
+            .body(correo.cuerpo.clone())
+            .map_err(|e| e.to_string())?;
+
+        self.transporte
+            .send(mensaje)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+// Desarrollo y pruebas: guarda cada correo como archivo de texto y lo muestra por consola
+pub struct MailerArchivo {
+    directorio: PathBuf,
+}
+
+#[async_trait]
+impl Mailer for MailerArchivo {
+    async fn enviar(&self, correo: &Correo) -> Result<(), String> {
+        let nombre = format!(
+            "{}_{}.txt",
+            Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            correo.para.replace(['@', '/', '\\'], "_")
+        );
+        let contenido = format!(
+            "Para: {}\nAsunto: {}\n\n{}\n",
+            correo.para, correo.asunto, correo.cuerpo
+        );
+
+        tokio::fs::create_dir_all(&self.directorio)
+            .await
+            .map_err(|e| e.to_string())?;
+        tokio::fs::write(self.directorio.join(&nombre), &contenido)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        println!("✉ Correo para {} guardado en {}", correo.para, nombre);
+        Ok(())
+    }
+}
+
+// Configura el mailer desde variables de entorno:
+// - MAILER: "smtp" o "archivo" (por defecto)
+// - SMTP_HOST, SMTP_PORT (587), SMTP_USER, SMTP_PASSWORD: servidor SMTP (STARTTLS)
+// - MAIL_FROM: remitente de los correos
+// - MAIL_OUTBOX_DIR: carpeta del mailer de archivo, por defecto "correos"
+// - APP_ENV: si vale "production", falta de MAILER es un error
+pub fn inicializar_desde_entorno() -> Result<(), String> {
+    let produccion = std::env::var("APP_ENV")
+        .map(|v| v.eq_ignore_ascii_case("production"))
+        .unwrap_or(false);
+
+    let tipo = match variable("MAILER") {
+        Some(tipo) => tipo,
+        None if produccion => {
+            return Err("MAILER es obligatorio cuando APP_ENV=production".to_string())
+        }
+        None => "archivo".to_string(),
+    };
+
+    let mailer: Box<dyn Mailer> = match tipo.as_str() {
+        "archivo" => Box::new(MailerArchivo {
+            directorio: PathBuf::from(
+                variable("MAIL_OUTBOX_DIR").unwrap_or_else(|| "correos".to_string()),
+            ),
+        }),
+        "smtp" => {
+            let host = variable("SMTP_HOST").ok_or("SMTP_HOST es obligatorio con MAILER=smtp")?;
+            let remitente = variable("MAIL_FROM")
+                .ok_or("MAIL_FROM es obligatorio con MAILER=smtp")?
+                .parse::<Mailbox>()
+                .map_err(|e| format!("MAIL_FROM inválido: {}", e))?;
+
+            let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)
+                .map_err(|e| format!("SMTP_HOST inválido: {}", e))?;
+            if let Some(puerto) = variable("SMTP_PORT") {
+                builder = builder.port(
+                    puerto
+                        .parse()
+                        .map_err(|_| format!("SMTP_PORT inválido: '{}'", puerto))?,
+                );
+            }
+            if let (Some(usuario), Some(password)) =
+                (variable("SMTP_USER"), variable("SMTP_PASSWORD"))
+            {
+                builder = builder.credentials(Credentials::new(usuario, password));
+            }
+
+            Box::new(MailerSmtp {
+                transporte: builder.build(),
+                remitente,
+            })
+        }
+        otro => return Err(format!("MAILER no soportado: '{}'", otro)),
+    };
+
+    MAILER
+        .set(mailer)
+        .map_err(|_| "El mailer ya estaba inicializado".to_string())
+}
+
+pub async fn enviar(correo: Correo) -> Result<(), String> {
+    MAILER
+        .get()
+        .expect("Mailer no inicializado")
+        .enviar(&correo)
+        .await
+}
+
+// URL de una página del frontend (APP_URL, por defecto http://localhost:8080)
+pub fn url(ruta: &str) -> String {
+    let base = variable("APP_URL").unwrap_or_else(|| "http://localhost:8080".to_string());
+    format!("{}{}", base.trim_end_matches('/'), ruta)
+}
+
+// Enlace del frontend que lleva un token como parámetro
+pub fn enlace(ruta: &str, token: &str) -> String {
+    format!("{}?token={}", url(ruta), token)
+}
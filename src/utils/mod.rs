@@ -1 +1,17 @@
 pub mod jwt;
+pub mod entorno;
+pub mod refresh_token;
+pub mod revocacion;
+pub mod sesiones;
+pub mod mailer;
+pub mod verificacion_email;
+pub mod token_opaco;
+pub mod intentos_login;
+pub mod totp;
+pub mod oidc;
+pub mod tokens_personales;
+pub mod exportacion;
+pub mod password;
+pub mod politica_password;
+pub mod busqueda;
+pub mod vencimiento;
@@ -0,0 +1,302 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::utils::{entorno::variable, token_opaco};
+
+static PROVEEDORES: OnceLock<HashMap<String, ConfigProveedor>> = OnceLock::new();
+static DESCUBRIMIENTOS: OnceLock<Mutex<HashMap<String, Descubrimiento>>> = OnceLock::new();
+static CLIENTE_HTTP: OnceLock<reqwest::Client> = OnceLock::new();
+
+// Proveedor de identidad configurado por variables de entorno
+pub struct ConfigProveedor {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+}
+
+// Documento /.well-known/openid-configuration (solo los campos usados)
+#[derive(Debug, Clone, Deserialize)]
+struct Descubrimiento {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RespuestaToken {
+    id_token: Option<String>,
+}
+
+// Claims del id_token que usa la API
+#[derive(Debug, Deserialize)]
+pub struct ClaimsOidc {
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: Option<serde_json::Value>, // Algunos proveedores lo envían como texto
+    pub name: Option<String>,
+    pub nonce: Option<String>,
+}
+
+impl ClaimsOidc {
+    // Email en minúsculas, solo si el proveedor lo ha verificado
+    pub fn email_verificado(&self) -> Option<String> {
+        let verificado = match &self.email_verified {
+            Some(serde_json::Value::Bool(v)) => *v,
+            Some(serde_json::Value::String(v)) => v == "true",
+            _ => false,
+        };
+        self.email
+            .as_ref()
+            .filter(|_| verificado)
+            .map(|email| email.trim().to_lowercase())
+    }
+}
+
+// Datos de una autorización iniciada; `url` es a donde se redirige al usuario
+pub struct SolicitudAutorizacion {
+    pub url: String,
+    pub state: String,
+    pub code_verifier: String,
+    pub nonce: String,
+}
+
+pub enum ErrorOidc {
+    Proveedor(String),      // El proveedor no responde o responde algo inesperado
+    TokenInvalido(String),  // El id_token no supera la validación
+}
+
+impl std::fmt::Display for ErrorOidc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorOidc::Proveedor(e) => write!(f, "error del proveedor: {}", e),
+            ErrorOidc::TokenInvalido(e) => write!(f, "id_token inválido: {}", e),
+        }
+    }
+}
+
+// Carga los proveedores desde variables de entorno:
+// - OIDC_PROVIDERS: nombres separados por comas, p. ej. "corp,google"
+// - OIDC_<NOMBRE>_ISSUER, OIDC_<NOMBRE>_CLIENT_ID, OIDC_<NOMBRE>_REDIRECT_URI: obligatorias
+// - OIDC_<NOMBRE>_CLIENT_SECRET: opcional (clientes públicos con PKCE)
+// - OIDC_<NOMBRE>_SCOPES: por defecto "openid email profile"
+pub fn inicializar_desde_entorno() -> Result<(), String> {
+    let mut proveedores = HashMap::new();
+
+    for nombre in variable("OIDC_PROVIDERS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|n| n.trim().to_lowercase())
+        .filter(|n| !n.is_empty())
+    {
+        if !nombre
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(format!("Nombre de proveedor OIDC inválido: '{}'", nombre));
+        }
+
+        let prefijo = format!("OIDC_{}_", nombre.to_uppercase().replace('-', "_"));
+        let obligatoria = |campo: &str| {
+            variable(&format!("{}{}", prefijo, campo))
+                .ok_or_else(|| format!("{}{} es obligatoria", prefijo, campo))
+        };
+
+        let config = ConfigProveedor {
+            issuer: obligatoria("ISSUER")?.trim_end_matches('/').to_string(),
+            client_id: obligatoria("CLIENT_ID")?,
+            client_secret: variable(&format!("{}CLIENT_SECRET", prefijo)),
+            redirect_uri: obligatoria("REDIRECT_URI")?,
+            scopes: variable(&format!("{}SCOPES", prefijo))
+                .unwrap_or_else(|| "openid email profile".to_string()),
+        };
+        proveedores.insert(nombre, config);
+    }
+
+    if !proveedores.is_empty() {
+        let mut nombres: Vec<&String> = proveedores.keys().collect();
+        nombres.sort();
+        println!("✓ Proveedores OIDC: {:?}", nombres);
+    }
+
+    PROVEEDORES
+        .set(proveedores)
+        .map_err(|_| "Los proveedores OIDC ya estaban inicializados".to_string())
+}
+
+pub fn proveedor(nombre: &str) -> Option<&'static ConfigProveedor> {
+    PROVEEDORES
+        .get()
+        .expect("Proveedores OIDC no inicializados")
+        .get(nombre)
+}
+
+fn cliente_http() -> &'static reqwest::Client {
+    CLIENTE_HTTP.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Cliente HTTP válido")
+    })
+}
+
+async fn obtener_json<T: for<'de> Deserialize<'de>>(url: &str) -> Result<T, ErrorOidc> {
+    cliente_http()
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| ErrorOidc::Proveedor(e.to_string()))?
+        .json::<T>()
+        .await
+        .map_err(|e| ErrorOidc::Proveedor(e.to_string()))
+}
+
+// Documento de descubrimiento, cacheado por issuer tras la primera consulta
+async fn descubrir(config: &ConfigProveedor) -> Result<Descubrimiento, ErrorOidc> {
+    let cache = DESCUBRIMIENTOS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(descubrimiento) = cache.lock().unwrap().get(&config.issuer) {
+        return Ok(descubrimiento.clone());
+    }
+
+    let descubrimiento: Descubrimiento = obtener_json(&format!(
+        "{}/.well-known/openid-configuration",
+        config.issuer
+    ))
+    .await?;
+
+    if descubrimiento.issuer.trim_end_matches('/') != config.issuer {
+        return Err(ErrorOidc::Proveedor(format!(
+            "el issuer anunciado '{}' no coincide con el configurado",
+            descubrimiento.issuer
+        )));
+    }
+
+    cache
+        .lock()
+        .unwrap()
+        .insert(config.issuer.clone(), descubrimiento.clone());
+    Ok(descubrimiento)
+}
+
+// Construye la URL de autorización con state, nonce y reto PKCE (S256)
+pub async fn url_autorizacion(
+    config: &ConfigProveedor,
+) -> Result<SolicitudAutorizacion, ErrorOidc> {
+    let descubrimiento = descubrir(config).await?;
+
+    let state = token_opaco::generar(32);
+    let nonce = token_opaco::generar(32);
+    let code_verifier = token_opaco::generar(64);
+    let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+    let url = reqwest::Url::parse_with_params(
+        &descubrimiento.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", config.client_id.as_str()),
+            ("redirect_uri", config.redirect_uri.as_str()),
+            ("scope", config.scopes.as_str()),
+            ("state", state.as_str()),
+            ("nonce", nonce.as_str()),
+            ("code_challenge", code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|e| ErrorOidc::Proveedor(e.to_string()))?;
+
+    Ok(SolicitudAutorizacion {
+        url: url.to_string(),
+        state,
+        code_verifier,
+        nonce,
+    })
+}
+
+// Canjea el código de autorización y devuelve los claims del id_token ya validado
+pub async fn canjear_codigo(
+    config: &ConfigProveedor,
+    code: &str,
+    code_verifier: &str,
+    nonce: &str,
+) -> Result<ClaimsOidc, ErrorOidc> {
+    let descubrimiento = descubrir(config).await?;
+
+    let mut formulario = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", config.redirect_uri.as_str()),
+        ("client_id", config.client_id.as_str()),
+        ("code_verifier", code_verifier),
+    ];
+    if let Some(secreto) = &config.client_secret {
+        formulario.push(("client_secret", secreto.as_str()));
+    }
+
+    let respuesta: RespuestaToken = cliente_http()
+        .post(&descubrimiento.token_endpoint)
+        .form(&formulario)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| ErrorOidc::Proveedor(e.to_string()))?
+        .json()
+        .await
+        .map_err(|e| ErrorOidc::Proveedor(e.to_string()))?;
+
+    let id_token = respuesta
+        .id_token
+        .ok_or_else(|| ErrorOidc::Proveedor("la respuesta no incluye id_token".to_string()))?;
+
+    validar_id_token(config, &descubrimiento, &id_token, nonce).await
+}
+
+async fn validar_id_token(
+    config: &ConfigProveedor,
+    descubrimiento: &Descubrimiento,
+    id_token: &str,
+    nonce: &str,
+) -> Result<ClaimsOidc, ErrorOidc> {
+    let cabecera = decode_header(id_token).map_err(|e| ErrorOidc::TokenInvalido(e.to_string()))?;
+
+    // Solo firmas asimétricas publicadas en el JWKS del proveedor
+    if matches!(
+        cabecera.alg,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    ) {
+        return Err(ErrorOidc::TokenInvalido(format!(
+            "algoritmo no admitido: {:?}",
+            cabecera.alg
+        )));
+    }
+
+    let jwks: JwkSet = obtener_json(&descubrimiento.jwks_uri).await?;
+    let jwk = match &cabecera.kid {
+        Some(kid) => jwks.find(kid),
+        None if jwks.keys.len() == 1 => jwks.keys.first(),
+        None => None,
+    }
+    .ok_or_else(|| ErrorOidc::TokenInvalido("clave de firma desconocida".to_string()))?;
+    let clave = DecodingKey::from_jwk(jwk).map_err(|e| ErrorOidc::TokenInvalido(e.to_string()))?;
+
+    let mut validacion = Validation::new(cabecera.alg);
+    validacion.set_issuer(&[&descubrimiento.issuer]);
+    validacion.set_audience(&[&config.client_id]);
+
+    let claims = decode::<ClaimsOidc>(id_token, &clave, &validacion)
+        .map_err(|e| ErrorOidc::TokenInvalido(e.to_string()))?
+        .claims;
+
+    match &claims.nonce {
+        Some(recibido) if token_opaco::iguales(recibido, nonce) => Ok(claims),
+        _ => Err(ErrorOidc::TokenInvalido("nonce incorrecto".to_string())),
+    }
+}
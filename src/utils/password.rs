@@ -0,0 +1,79 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use std::sync::OnceLock;
+
+use crate::utils::entorno::numero;
+
+static PARAMETROS: OnceLock<Params> = OnceLock::new();
+
+// Parámetros de Argon2id (por defecto los mínimos recomendados por OWASP):
+// - ARGON2_MEMORY_KIB: memoria en KiB, por defecto 19456 (19 MiB)
+// - ARGON2_ITERATIONS: iteraciones, por defecto 2
+// - ARGON2_PARALLELISM: hilos, por defecto 1
+pub fn inicializar_desde_entorno() -> Result<(), String> {
+    let parametros = Params::new(
+        numero("ARGON2_MEMORY_KIB", 19 * 1024)?,
+        numero("ARGON2_ITERATIONS", 2)?,
+        numero("ARGON2_PARALLELISM", 1)?,
+        None,
+    )
+    .map_err(|e| format!("Parámetros de Argon2 inválidos: {}", e))?;
+
+    PARAMETROS
+        .set(parametros)
+        .map_err(|_| "Los parámetros de Argon2 ya estaban inicializados".to_string())
+}
+
+fn argon2() -> Argon2<'static> {
+    let parametros = PARAMETROS.get().expect("Parámetros de Argon2 no inicializados");
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, parametros.clone())
+}
+
+fn es_bcrypt(hash: &str) -> bool {
+    hash.starts_with("$2")
+}
+
+// Hash Argon2id en formato PHC ($argon2id$v=19$m=...,t=...,p=...$sal$hash)
+pub fn hashear(password: &str) -> Result<String, String> {
+    let sal = SaltString::generate(&mut OsRng);
+    argon2()
+        .hash_password(password.as_bytes(), &sal)
+        .map(|hash| hash.to_string())
+        .map_err(|e| e.to_string())
+}
+
+// Verifica contra un hash Argon2 o contra uno bcrypt anterior a la migración
+pub fn verificar(password: &str, hash: &str) -> Result<bool, String> {
+    if es_bcrypt(hash) {
+        return bcrypt::verify(password, hash).map_err(|e| e.to_string());
+    }
+
+    let hash = PasswordHash::new(hash).map_err(|e| e.to_string())?;
+    match argon2().verify_password(password.as_bytes(), &hash) {
+        Ok(()) => Ok(true),
+        Err(argon2::password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Indica si el hash debe regenerarse: es bcrypt o usa otros parámetros de Argon2
+pub fn necesita_rehash(hash: &str) -> bool {
+    if es_bcrypt(hash) {
+        return true;
+    }
+
+    let actuales = PARAMETROS.get().expect("Parámetros de Argon2 no inicializados");
+    match PasswordHash::new(hash) {
+        Ok(hash) => {
+            hash.algorithm != argon2::ARGON2ID_IDENT
+                || Params::try_from(&hash).map_or(true, |p| {
+                    p.m_cost() != actuales.m_cost()
+                        || p.t_cost() != actuales.t_cost()
+                        || p.p_cost() != actuales.p_cost()
+                })
+        }
+        Err(_) => true,
+    }
+}
@@ -0,0 +1,255 @@
+use axum::{http::StatusCode, Json};
+use sha1::{Digest, Sha1};
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use crate::utils::entorno::{numero, variable};
+
+// bcrypt ignora lo que pase de 72 bytes; se mantiene el límite mientras queden hashes bcrypt
+const MAX_BYTES: usize = 72;
+
+static POLITICA: OnceLock<Politica> = OnceLock::new();
+
+struct Politica {
+    longitud_minima: usize,
+    clases_minimas: usize,
+    comprometidas: HashSet<[u8; 20]>,
+}
+
+// Carga la política de contraseñas:
+// - PASSWORD_MIN_LENGTH: longitud mínima en caracteres, por defecto 8
+// - PASSWORD_MIN_CLASSES: clases exigidas de entre minúsculas, mayúsculas, dígitos y
+//   símbolos, por defecto 2
+// - PASSWORD_BREACHED_HASHES_FILE: lista opcional de contraseñas comprometidas, un SHA-1
+//   en hexadecimal por línea; se admite el formato "HASH:apariciones" de HIBP
+pub fn inicializar_desde_entorno() -> Result<(), String> {
+    let longitud_minima = numero("PASSWORD_MIN_LENGTH", 8)?;
+    if !(1..=MAX_BYTES).contains(&longitud_minima) {
+        return Err(format!("PASSWORD_MIN_LENGTH debe estar entre 1 y {}", MAX_BYTES));
+    }
+    let clases_minimas = numero("PASSWORD_MIN_CLASSES", 2)?;
+    if clases_minimas > 4 {
+        return Err("PASSWORD_MIN_CLASSES debe estar entre 0 y 4".to_string());
+    }
+
+    let mut hashes = HashSet::new();
+
+    if let Some(ruta) = variable("PASSWORD_BREACHED_HASHES_FILE") {
+        let contenido = std::fs::read_to_string(&ruta)
+            .map_err(|e| format!("No se pudo leer PASSWORD_BREACHED_HASHES_FILE: {}", e))?;
+
+        for (numero, linea) in contenido.lines().enumerate() {
+            let hex = linea.split(':').next().unwrap_or_default().trim();
+            if hex.is_empty() {
+                continue;
+            }
+            let hash = decodificar_sha1(hex).ok_or_else(|| {
+                format!("Hash SHA-1 inválido en la línea {} de la lista", numero + 1)
+            })?;
+            hashes.insert(hash);
+        }
+        println!("✓ Lista de contraseñas comprometidas: {} hashes", hashes.len());
+    }
+
+    POLITICA
+        .set(Politica {
+            longitud_minima,
+            clases_minimas,
+            comprometidas: hashes,
+        })
+        .map_err(|_| "La política de contraseñas ya estaba cargada".to_string())
+}
+
+fn politica() -> &'static Politica {
+    POLITICA.get().expect("Política de contraseñas no inicializada")
+}
+
+fn decodificar_sha1(hex: &str) -> Option<[u8; 20]> {
+    if hex.len() != 40 {
+        return None;
+    }
+    let mut hash = [0u8; 20];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(hash)
+}
+
+fn esta_comprometida(politica: &Politica, password: &str) -> bool {
+    politica
+        .comprometidas
+        .contains(&<[u8; 20]>::from(Sha1::digest(password.as_bytes())))
+}
+
+// Fragmentos del email y del nombre que no pueden aparecer en la contraseña
+fn datos_personales(email: &str, name: &str) -> Vec<String> {
+    let mut fragmentos: Vec<String> = name
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect();
+    fragmentos.push(email.to_lowercase());
+    if let Some((local, _)) = email.split_once('@') {
+        fragmentos.push(local.to_lowercase());
+    }
+    fragmentos.retain(|f| f.chars().count() >= 3);
+    fragmentos
+}
+
+// Comprueba la contraseña y devuelve todos los incumplimientos
+pub fn validar(password: &str, email: &str, name: &str) -> Result<(), Vec<String>> {
+    comprobar(politica(), password, email, name)
+}
+
+fn comprobar(
+    politica: &Politica,
+    password: &str,
+    email: &str,
+    name: &str,
+) -> Result<(), Vec<String>> {
+    let mut errores = Vec::new();
+
+    let minima = politica.longitud_minima;
+    if password.chars().count() < minima {
+        errores.push(format!("Debe tener al menos {} caracteres", minima));
+    }
+    if password.len() > MAX_BYTES {
+        errores.push(format!("No puede superar los {} bytes", MAX_BYTES));
+    }
+
+    let clases = [
+        password.chars().any(|c| c.is_lowercase()),
+        password.chars().any(|c| c.is_uppercase()),
+        password.chars().any(|c| c.is_numeric()),
+        password.chars().any(|c| !c.is_alphanumeric()),
+    ]
+    .iter()
+    .filter(|presente| **presente)
+    .count();
+    if clases < politica.clases_minimas {
+        errores.push(format!(
+            "Debe combinar al menos {} tipos de caracteres (minúsculas, mayúsculas, dígitos, símbolos)",
+            politica.clases_minimas
+        ));
+    }
+
+    let password_min = password.to_lowercase();
+    if datos_personales(email, name)
+        .iter()
+        .any(|fragmento| password_min.contains(fragmento.as_str()))
+    {
+        errores.push("No puede contener tu nombre ni tu email".to_string());
+    }
+
+    if esta_comprometida(politica, password) {
+        errores.push("Aparece en filtraciones de contraseñas conocidas".to_string());
+    }
+
+    if errores.is_empty() {
+        Ok(())
+    } else {
+        Err(errores)
+    }
+}
+
+// Respuesta 400 con la lista de incumplimientos
+pub fn respuesta_rechazo(errores: Vec<String>) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({
+            "success": false,
+            "message": "La contraseña no cumple la política de seguridad",
+            "errors": errores
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn politica_de_prueba(comprometidas: &[&str]) -> Politica {
+        Politica {
+            longitud_minima: 8,
+            clases_minimas: 2,
+            comprometidas: comprometidas
+                .iter()
+                .map(|p| <[u8; 20]>::from(Sha1::digest(p.as_bytes())))
+                .collect(),
+        }
+    }
+
+    fn errores(politica: &Politica, password: &str) -> Vec<String> {
+        comprobar(politica, password, "ana@ejemplo.com", "Ana Pérez")
+            .err()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn acepta_una_contrasena_que_cumple_la_politica() {
+        let politica = politica_de_prueba(&[]);
+        assert!(comprobar(&politica, "Tortuga-29", "ana@ejemplo.com", "Ana").is_ok());
+    }
+
+    #[test]
+    fn longitud_minima_en_caracteres() {
+        let politica = politica_de_prueba(&[]);
+        assert_eq!(errores(&politica, "Abc12"), vec!["Debe tener al menos 8 caracteres"]);
+        // Ocho caracteres aunque ocupen más bytes
+        assert!(errores(&politica, "ñandú1ñú").is_empty());
+    }
+
+    #[test]
+    fn maximo_en_bytes() {
+        let password = format!("A1{}", "ñ".repeat(36));
+        assert_eq!(
+            errores(&politica_de_prueba(&[]), &password),
+            vec!["No puede superar los 72 bytes"]
+        );
+    }
+
+    #[test]
+    fn exige_clases_de_caracteres() {
+        let politica = politica_de_prueba(&[]);
+        assert_eq!(errores(&politica, "tortugalenta").len(), 1);
+        assert!(errores(&politica, "tortugalenta7").is_empty());
+        assert!(errores(&politica, "tortuga lenta").is_empty());
+
+        let sin_exigencia = Politica {
+            clases_minimas: 0,
+            ..politica_de_prueba(&[])
+        };
+        assert!(errores(&sin_exigencia, "tortugalenta").is_empty());
+    }
+
+    #[test]
+    fn rechaza_nombre_y_email() {
+        let politica = politica_de_prueba(&[]);
+        let esperado = vec!["No puede contener tu nombre ni tu email"];
+        assert_eq!(errores(&politica, "Pérez-2024x"), esperado);
+        assert_eq!(errores(&politica, "xx-ANA@ejemplo.com"), esperado);
+        // Los fragmentos de menos de tres caracteres no cuentan
+        assert!(comprobar(&politica, "Tortuga-29", "t@x.com", "To").is_ok());
+    }
+
+    #[test]
+    fn rechaza_contrasenas_comprometidas() {
+        let politica = politica_de_prueba(&["Password1"]);
+        assert_eq!(
+            errores(&politica, "Password1"),
+            vec!["Aparece en filtraciones de contraseñas conocidas"]
+        );
+        assert!(errores(&politica, "Password2").is_empty());
+    }
+
+    #[test]
+    fn devuelve_todos_los_incumplimientos() {
+        assert_eq!(errores(&politica_de_prueba(&["ana"]), "ana").len(), 4);
+    }
+
+    #[test]
+    fn decodifica_el_formato_de_hibp() {
+        assert!(decodificar_sha1("5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8").is_some());
+        assert!(decodificar_sha1("5BAA61E4").is_none());
+        assert!(decodificar_sha1("ZZAA61E4C9B93F3F0682250B6CF8331B7EE68FD8").is_none());
+    }
+}
@@ -0,0 +1,136 @@
+use bson::oid::ObjectId;
+use chrono::{Duration, Utc};
+use mongodb::{bson::doc, Database};
+use std::sync::OnceLock;
+
+use crate::{
+    db::mongo::obtener_coleccion_refresh_tokens,
+    models::refresh_token::RefreshToken,
+    utils::{entorno::numero, token_opaco},
+};
+
+const DIAS_REFRESH_POR_DEFECTO: i64 = 30;
+
+static DURACION_REFRESH: OnceLock<Duration> = OnceLock::new();
+
+// Resultado de intentar rotar un refresh token
+pub enum Rotacion {
+    Valido {
+        usuario_id: ObjectId,
+        familia: String,
+        refresh_token: String,
+    },
+    Invalido,
+    Reutilizado { usuario_id: ObjectId, familia: String },
+}
+
+// Duración de los refresh tokens (REFRESH_TOKEN_TTL_DAYS, por defecto 30 días)
+pub fn inicializar_desde_entorno() -> Result<(), String> {
+    let dias = numero("REFRESH_TOKEN_TTL_DAYS", DIAS_REFRESH_POR_DEFECTO)?;
+    let duracion = Duration::try_days(dias)
+        .filter(|_| dias > 0)
+        .ok_or_else(|| "REFRESH_TOKEN_TTL_DAYS debe ser mayor que 0".to_string())?;
+
+    DURACION_REFRESH
+        .set(duracion)
+        .map_err(|_| "La duración de los refresh tokens ya estaba inicializada".to_string())
+}
+
+pub fn duracion_refresh() -> Duration {
+    *DURACION_REFRESH.get().expect("Duración de los refresh tokens no inicializada")
+}
+
+// Identificador de una nueva sesión (familia de refresh tokens)
+pub fn nueva_familia() -> String {
+    token_opaco::generar(24)
+}
+
+// Crea y guarda un refresh token dentro de la familia (sesión) indicada
+pub async fn emitir_refresh_token(
+    db: &Database,
+    usuario_id: ObjectId,
+    familia: &str,
+) -> Result<String, mongodb::error::Error> {
+    let token = token_opaco::generar(48);
+    let expires_at = Utc::now()
+        .checked_add_signed(duracion_refresh())
+        .expect("Fecha válida");
+
+    obtener_coleccion_refresh_tokens(db)
+        .insert_one(
+            RefreshToken::nuevo(usuario_id, familia.to_string(), token_opaco::hash(&token), expires_at),
+            None,
+        )
+        .await?;
+
+    Ok(token)
+}
+
+// Marca el token como usado y emite el siguiente de la misma familia.
+// Presentar un token ya usado revoca la familia completa.
+pub async fn rotar_refresh_token(
+    db: &Database,
+    token: &str,
+) -> Result<Rotacion, mongodb::error::Error> {
+    let coleccion = obtener_coleccion_refresh_tokens(db);
+    let token_hash = token_opaco::hash(token);
+
+    // Marcado atómico: solo una petición concurrente puede consumir el token
+    let consumido = coleccion
+        .find_one_and_update(
+            doc! {
+                "token_hash": &token_hash,
+                "usado": false,
+                "revocado": false,
+                "expires_at": { "$gt": Utc::now() },
+            },
+            doc! { "$set": { "usado": true } },
+            None,
+        )
+        .await?;
+
+    if let Some(actual) = consumido {
+        let refresh_token = emitir_refresh_token(db, actual.usuario_id, &actual.familia).await?;
+        return Ok(Rotacion::Valido {
+            usuario_id: actual.usuario_id,
+            familia: actual.familia,
+            refresh_token,
+        });
+    }
+
+    match coleccion.find_one(doc! { "token_hash": &token_hash }, None).await? {
+        Some(existente) if existente.usado => {
+            revocar_familia(db, &existente.familia).await?;
+            Ok(Rotacion::Reutilizado {
+                usuario_id: existente.usuario_id,
+                familia: existente.familia,
+            })
+        }
+        _ => Ok(Rotacion::Invalido),
+    }
+}
+
+pub async fn revocar_familia(db: &Database, familia: &str) -> Result<(), mongodb::error::Error> {
+    obtener_coleccion_refresh_tokens(db)
+        .update_many(
+            doc! { "familia": familia },
+            doc! { "$set": { "revocado": true } },
+            None,
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn revocar_todas_del_usuario(
+    db: &Database,
+    usuario_id: ObjectId,
+) -> Result<(), mongodb::error::Error> {
+    obtener_coleccion_refresh_tokens(db)
+        .update_many(
+            doc! { "usuario_id": usuario_id },
+            doc! { "$set": { "revocado": true } },
+            None,
+        )
+        .await?;
+    Ok(())
+}
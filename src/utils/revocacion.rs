@@ -0,0 +1,71 @@
+use bson::oid::ObjectId;
+use chrono::Utc;
+use mongodb::{bson::doc, Database};
+
+use crate::{
+    db::mongo::obtener_coleccion_revocaciones,
+    models::revocacion::Revocacion,
+    utils::jwt::{duracion_acceso, Claims},
+};
+
+// Revoca todos los tokens de acceso emitidos hasta ahora para el usuario
+pub async fn revocar_todos(db: &Database, usuario_id: ObjectId) -> Result<(), mongodb::error::Error> {
+    let ahora = Utc::now();
+
+    obtener_coleccion_revocaciones(db)
+        .insert_one(
+            Revocacion {
+                id: None,
+                usuario_id,
+                sid: None,
+                emitidos_hasta_ms: Some(ahora.timestamp_millis()),
+                expires_at: ahora + duracion_acceso(),
+            },
+            None,
+        )
+        .await?;
+    Ok(())
+}
+
+// Revoca los tokens de acceso de una sesión (dispositivo) concreta
+pub async fn revocar_sesion(
+    db: &Database,
+    usuario_id: ObjectId,
+    sid: &str,
+) -> Result<(), mongodb::error::Error> {
+    obtener_coleccion_revocaciones(db)
+        .insert_one(
+            Revocacion {
+                id: None,
+                usuario_id,
+                sid: Some(sid.to_string()),
+                emitidos_hasta_ms: None,
+                expires_at: Utc::now() + duracion_acceso(),
+            },
+            None,
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn esta_revocado(db: &Database, claims: &Claims) -> Result<bool, mongodb::error::Error> {
+    let usuario_id = match ObjectId::parse_str(&claims.sub) {
+        Ok(id) => id,
+        Err(_) => return Ok(true),
+    };
+
+    let revocacion = obtener_coleccion_revocaciones(db)
+        .find_one(
+            doc! {
+                "usuario_id": usuario_id,
+                "$or": [
+                    { "sid": &claims.sid },
+                    { "emitidos_hasta_ms": { "$gte": claims.iat_ms } },
+                ],
+            },
+            None,
+        )
+        .await?;
+
+    Ok(revocacion.is_some())
+}
@@ -0,0 +1,118 @@
+use bson::oid::ObjectId;
+use chrono::{Duration, Utc};
+use futures::stream::TryStreamExt;
+use mongodb::{bson::doc, Database};
+
+use crate::{
+    db::mongo::obtener_coleccion_sesiones,
+    models::sesion::Sesion,
+    utils::{
+        refresh_token::{duracion_refresh, revocar_familia, revocar_todas_del_usuario},
+        revocacion::{revocar_sesion, revocar_todos},
+    },
+};
+
+// Registra la sesión creada en un login o registro
+pub async fn registrar_sesion(
+    db: &Database,
+    usuario_id: ObjectId,
+    sid: &str,
+    user_agent: Option<String>,
+    ip: Option<String>,
+) -> Result<(), mongodb::error::Error> {
+    let ahora = Utc::now();
+
+    obtener_coleccion_sesiones(db)
+        .insert_one(
+            Sesion {
+                id: None,
+                usuario_id,
+                sid: sid.to_string(),
+                user_agent,
+                ip,
+                created_at: ahora,
+                last_seen_at: ahora,
+                expires_at: ahora + duracion_refresh(),
+            },
+            None,
+        )
+        .await?;
+    Ok(())
+}
+
+// Indica si la sesión se inició (con login, no con renovación) en los últimos `ventana`;
+// sirve de confirmación para cuentas sin contraseña propia
+pub async fn iniciada_hace_menos_de(
+    db: &Database,
+    usuario_id: ObjectId,
+    sid: &str,
+    ventana: Duration,
+) -> Result<bool, mongodb::error::Error> {
+    let sesion = obtener_coleccion_sesiones(db)
+        .find_one(doc! { "usuario_id": usuario_id, "sid": sid }, None)
+        .await?;
+    Ok(sesion.is_some_and(|s| s.created_at > Utc::now() - ventana))
+}
+
+// Actualiza la última actividad; se llama en cada renovación de token
+pub async fn actualizar_actividad(
+    db: &Database,
+    sid: &str,
+    ip: Option<String>,
+) -> Result<(), mongodb::error::Error> {
+    let ahora = Utc::now();
+    let mut cambios = doc! {
+        "last_seen_at": ahora,
+        "expires_at": ahora + duracion_refresh(),
+    };
+    if let Some(ip) = ip {
+        cambios.insert("ip", ip);
+    }
+
+    obtener_coleccion_sesiones(db)
+        .update_one(doc! { "sid": sid }, doc! { "$set": cambios }, None)
+        .await?;
+    Ok(())
+}
+
+// Cierra una sesión: revoca sus refresh tokens y tokens de acceso y la elimina
+pub async fn cerrar_sesion(
+    db: &Database,
+    usuario_id: ObjectId,
+    sid: &str,
+) -> Result<(), mongodb::error::Error> {
+    revocar_familia(db, sid).await?;
+    revocar_sesion(db, usuario_id, sid).await?;
+    obtener_coleccion_sesiones(db)
+        .delete_one(doc! { "usuario_id": usuario_id, "sid": sid }, None)
+        .await?;
+    Ok(())
+}
+
+// Cierra todas las sesiones del usuario salvo la indicada (p. ej. al cambiar la contraseña)
+pub async fn cerrar_otras(
+    db: &Database,
+    usuario_id: ObjectId,
+    sid_actual: &str,
+) -> Result<(), mongodb::error::Error> {
+    let sesiones: Vec<Sesion> = obtener_coleccion_sesiones(db)
+        .find(doc! { "usuario_id": usuario_id, "sid": { "$ne": sid_actual } }, None)
+        .await?
+        .try_collect()
+        .await?;
+
+    for sesion in sesiones {
+        cerrar_sesion(db, usuario_id, &sesion.sid).await?;
+    }
+    Ok(())
+}
+
+// Cierra todas las sesiones del usuario
+pub async fn cerrar_todas(db: &Database, usuario_id: ObjectId) -> Result<(), mongodb::error::Error> {
+    revocar_todas_del_usuario(db, usuario_id).await?;
+    revocar_todos(db, usuario_id).await?;
+    obtener_coleccion_sesiones(db)
+        .delete_many(doc! { "usuario_id": usuario_id }, None)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+// Cadena alfanumérica aleatoria para tokens opacos
+pub fn generar(longitud: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(longitud)
+        .map(char::from)
+        .collect()
+}
+
+// Hash SHA-256 en hexadecimal; en la base de datos solo se guarda este valor
+pub fn hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+// Compara dos cadenas en tiempo constante
+pub fn iguales(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
+
+// Compara el hash del token con el almacenado en tiempo constante
+pub fn coincide(token: &str, hash_guardado: &str) -> bool {
+    iguales(&hash(token), hash_guardado)
+}
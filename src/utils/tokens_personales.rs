@@ -0,0 +1,125 @@
+use chrono::{Duration, Utc};
+use mongodb::{bson::doc, Database};
+
+use crate::{
+    db::mongo::{obtener_coleccion_tokens_personales, obtener_coleccion_usuarios},
+    models::token_personal::{Scope, TokenPersonal},
+    utils::{jwt::Claims, token_opaco},
+};
+
+// Prefijo que distingue los tokens personales de los JWT en la cabecera Authorization
+pub const PREFIJO: &str = "pat_";
+
+pub enum Autenticacion {
+    Valido(Claims),
+    Invalido,
+    Expirado,
+}
+
+pub fn generar() -> String {
+    format!("{}{}", PREFIJO, token_opaco::generar(40))
+}
+
+// Scope que exige cada ruta a un token personal; None si la ruta no admite estos tokens
+pub fn scope_requerido(metodo: &axum::http::Method, ruta: &str) -> Option<Scope> {
+    if ruta != "/tareas" && !ruta.starts_with("/tareas/") {
+        return None;
+    }
+
+    if metodo == axum::http::Method::GET {
+        Some(Scope::TareasLectura)
+    } else {
+        Some(Scope::TareasEscritura)
+    }
+}
+
+// Valida un token personal y construye los Claims equivalentes a los de un JWT
+pub async fn autenticar(
+    db: &Database,
+    token: &str,
+) -> Result<Autenticacion, mongodb::error::Error> {
+    let coleccion = obtener_coleccion_tokens_personales(db);
+    let ahora = Utc::now();
+
+    let token_personal: TokenPersonal = match coleccion
+        .find_one(doc! { "token_hash": token_opaco::hash(token) }, None)
+        .await?
+    {
+        Some(t) => t,
+        None => return Ok(Autenticacion::Invalido),
+    };
+
+    // El índice TTL puede tardar hasta un minuto en borrar los caducados
+    if token_personal.expires_at.is_some_and(|expira| expira <= ahora) {
+        return Ok(Autenticacion::Expirado);
+    }
+
+    // Un usuario deshabilitado no puede seguir usando sus tokens
+    let activo = obtener_coleccion_usuarios(db)
+        .find_one(
+            doc! { "_id": token_personal.usuario_id, "deshabilitado": { "$ne": true } },
+            None,
+        )
+        .await?
+        .is_some();
+    if !activo {
+        return Ok(Autenticacion::Invalido);
+    }
+
+    // Último uso con precisión de un minuto, para no escribir en cada petición
+    coleccion
+        .update_one(
+            doc! {
+                "_id": token_personal.id,
+                "$or": [
+                    { "last_used_at": { "$lt": ahora - Duration::minutes(1) } },
+                    { "last_used_at": null },
+                ],
+            },
+            doc! { "$set": { "last_used_at": ahora } },
+            None,
+        )
+        .await?;
+
+    Ok(Autenticacion::Valido(Claims {
+        sub: token_personal.usuario_id.to_hex(),
+        exp: token_personal
+            .expires_at
+            .map(|expira| expira.timestamp() as usize)
+            .unwrap_or(usize::MAX),
+        iat: token_personal.created_at.timestamp() as usize,
+        iat_ms: token_personal.created_at.timestamp_millis(),
+        sid: String::new(), // No pertenece a ninguna sesión
+        roles: Vec::new(),  // Nunca concede permisos de administración
+        scopes: Some(token_personal.scopes),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Method;
+
+    #[test]
+    fn scope_por_ruta_y_metodo() {
+        let casos = [
+            (Method::GET, "/tareas", Some(Scope::TareasLectura)),
+            (Method::GET, "/tareas/search", Some(Scope::TareasLectura)),
+            (Method::GET, "/tareas/65a1b2c3d4e5f6a7b8c9d0e1", Some(Scope::TareasLectura)),
+            (Method::POST, "/tareas", Some(Scope::TareasEscritura)),
+            (Method::PUT, "/tareas/65a1b2c3d4e5f6a7b8c9d0e1", Some(Scope::TareasEscritura)),
+            (Method::DELETE, "/tareas/65a1b2c3d4e5f6a7b8c9d0e1", Some(Scope::TareasEscritura)),
+            // Fuera de /tareas los tokens personales no sirven
+            (Method::GET, "/tareasx", None),
+            (Method::GET, "/auth/me", None),
+            (Method::POST, "/auth/logout", None),
+            (Method::POST, "/auth/tokens", None),
+            (Method::GET, "/admin/usuarios", None),
+            (Method::GET, "/", None),
+        ];
+
+        for (metodo, ruta, esperado) in casos {
+            assert_eq!(scope_requerido(&metodo, ruta), esperado, "{} {}", metodo, ruta);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use rand::RngCore;
+use std::sync::OnceLock;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::utils::{entorno::variable, token_opaco};
+
+const DIGITOS: usize = 6;
+const PASO_SEGUNDOS: u64 = 30;
+const CODIGOS_RECUPERACION: usize = 10;
+
+static EMISOR: OnceLock<String> = OnceLock::new();
+
+// Nombre mostrado en la app de autenticación (TOTP_ISSUER, por defecto "API Tareas").
+// No puede llevar ':' porque separa el emisor de la cuenta en la URI otpauth://
+pub fn inicializar_desde_entorno() -> Result<(), String> {
+    let emisor = variable("TOTP_ISSUER").unwrap_or_else(|| "API Tareas".to_string());
+    if emisor.contains(':') {
+        return Err(format!("TOTP_ISSUER no puede contener ':': '{}'", emisor));
+    }
+
+    EMISOR
+        .set(emisor)
+        .map_err(|_| "El emisor TOTP ya estaba inicializado".to_string())
+}
+
+fn emisor() -> &'static str {
+    EMISOR.get().expect("Emisor TOTP no inicializado")
+}
+
+fn construir(secreto: &str, email: &str) -> Result<TOTP, String> {
+    let bytes = Secret::Encoded(secreto.to_string())
+        .to_bytes()
+        .map_err(|e| format!("{:?}", e))?;
+
+    // skew 0: la tolerancia de ±1 paso se aplica en `verificar_codigo`
+    TOTP::new(
+        Algorithm::SHA1,
+        DIGITOS,
+        0,
+        PASO_SEGUNDOS,
+        bytes,
+        Some(emisor().to_string()),
+        email.to_string(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+// Secreto aleatorio de 160 bits codificado en base32
+pub fn generar_secreto() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    Secret::Raw(bytes.to_vec()).to_encoded().to_string()
+}
+
+// URI otpauth:// para generar el código QR en el frontend
+pub fn uri_otpauth(secreto: &str, email: &str) -> Result<String, String> {
+    Ok(construir(secreto, email)?.get_url())
+}
+
+// Comprueba el código con una tolerancia de un paso y devuelve el paso usado.
+// Se rechazan pasos iguales o anteriores a `ultimo_paso` para impedir reutilizar un código.
+pub fn verificar_codigo(secreto: &str, codigo: &str, ultimo_paso: Option<i64>) -> Option<i64> {
+    verificar_codigo_en(secreto, codigo, ultimo_paso, chrono::Utc::now().timestamp())
+}
+
+fn verificar_codigo_en(
+    secreto: &str,
+    codigo: &str,
+    ultimo_paso: Option<i64>,
+    ahora: i64,
+) -> Option<i64> {
+    let totp = construir(secreto, "").ok()?;
+    let paso_actual = ahora / PASO_SEGUNDOS as i64;
+
+    [paso_actual - 1, paso_actual, paso_actual + 1]
+        .into_iter()
+        .filter(|paso| ultimo_paso.is_none_or(|ultimo| *paso > ultimo))
+        .find(|paso| {
+            let esperado = totp.generate((*paso as u64) * PASO_SEGUNDOS);
+            token_opaco::iguales(&esperado, codigo.trim())
+        })
+}
+
+// Códigos de un solo uso con formato "xxxxx-xxxxx"
+pub fn generar_codigos_recuperacion() -> Vec<String> {
+    (0..CODIGOS_RECUPERACION)
+        .map(|_| {
+            let codigo = token_opaco::generar(10).to_lowercase();
+            format!("{}-{}", &codigo[..5], &codigo[5..])
+        })
+        .collect()
+}
+
+// Normaliza un código de recuperación antes de calcular su hash
+pub fn normalizar_codigo_recuperacion(codigo: &str) -> String {
+    codigo.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AHORA: i64 = 1_715_342_415; // paso 57_178_080, a mitad de paso
+    const PASO: i64 = AHORA / PASO_SEGUNDOS as i64;
+
+    fn codigo(secreto: &str, paso: i64) -> String {
+        construir(secreto, "").unwrap().generate(paso as u64 * PASO_SEGUNDOS)
+    }
+
+    fn preparar() -> String {
+        let _ = inicializar_desde_entorno();
+        generar_secreto()
+    }
+
+    #[test]
+    fn acepta_un_paso_de_tolerancia() {
+        let secreto = preparar();
+        for paso in [PASO - 1, PASO, PASO + 1] {
+            let codigo = codigo(&secreto, paso);
+            assert_eq!(verificar_codigo_en(&secreto, &codigo, None, AHORA), Some(paso));
+        }
+    }
+
+    #[test]
+    fn rechaza_codigos_fuera_de_la_ventana() {
+        let secreto = preparar();
+        for paso in [PASO - 2, PASO + 2] {
+            let codigo = codigo(&secreto, paso);
+            assert_eq!(verificar_codigo_en(&secreto, &codigo, None, AHORA), None);
+        }
+        assert_eq!(verificar_codigo_en(&secreto, "abcdef", None, AHORA), None);
+    }
+
+    #[test]
+    fn rechaza_reutilizar_un_codigo() {
+        let secreto = preparar();
+        let actual = codigo(&secreto, PASO);
+        assert_eq!(verificar_codigo_en(&secreto, &actual, Some(PASO), AHORA), None);
+
+        // Tampoco vale uno anterior al último usado, pero sí el siguiente
+        let anterior = codigo(&secreto, PASO - 1);
+        assert_eq!(verificar_codigo_en(&secreto, &anterior, Some(PASO), AHORA), None);
+        let siguiente = codigo(&secreto, PASO + 1);
+        assert_eq!(
+            verificar_codigo_en(&secreto, &siguiente, Some(PASO), AHORA),
+            Some(PASO + 1)
+        );
+    }
+
+    #[test]
+    fn tolera_espacios_alrededor_del_codigo() {
+        let secreto = preparar();
+        let codigo = format!(" {} ", codigo(&secreto, PASO));
+        assert_eq!(verificar_codigo_en(&secreto, &codigo, None, AHORA), Some(PASO));
+    }
+}
@@ -0,0 +1,275 @@
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use mongodb::bson::{doc, Bson, Document};
+
+use crate::models::tarea::{EstadoTarea, Vencimiento, Vista};
+
+// Años admitidos en las fechas de vencimiento
+const PRIMER_ANIO: i32 = 2000;
+const ULTIMO_ANIO: i32 = 2100;
+
+// Zona horaria IANA (p. ej. "Europe/Madrid"); UTC si no se indica
+pub fn zona(nombre: Option<&str>) -> Result<Tz, String> {
+    match nombre.map(str::trim).filter(|n| !n.is_empty()) {
+        Some(nombre) => nombre
+            .parse::<Tz>()
+            .map_err(|_| format!("Zona horaria desconocida: '{}'", nombre)),
+        None => Ok(Tz::UTC),
+    }
+}
+
+// Instante en que empieza una hora local; si el cambio de horario la repite se toma
+// la primera y si no existe es un error
+fn instante_local(zona: Tz, local: NaiveDateTime) -> Result<DateTime<Utc>, String> {
+    match zona.from_local_datetime(&local) {
+        LocalResult::Single(fecha) | LocalResult::Ambiguous(fecha, _) => {
+            Ok(fecha.with_timezone(&Utc))
+        }
+        LocalResult::None => Err(format!("La hora {} no existe en la zona {}", local, zona)),
+    }
+}
+
+fn inicio_dia(zona: Tz, dia: NaiveDate) -> Result<DateTime<Utc>, String> {
+    // Algunas zonas cambian de horario a medianoche: se toma la primera hora existente
+    (0..3)
+        .filter_map(|hora| dia.and_hms_opt(hora, 0, 0))
+        .find_map(|local| instante_local(zona, local).ok())
+        .ok_or_else(|| format!("El día {} no existe en la zona {}", dia, zona))
+}
+
+// Interpreta la fecha de vencimiento recibida:
+// - RFC 3339 con desplazamiento ("2024-05-10T17:00:00+02:00"): instante exacto
+// - Fecha y hora sin desplazamiento ("2024-05-10T17:00"): hora local de `zona_horaria`
+// - Solo fecha ("2024-05-10"): todo el día en `zona_horaria`
+pub fn interpretar(valor: &str, zona_horaria: Option<&str>) -> Result<Vencimiento, String> {
+    let valor = valor.trim();
+    let tz = zona(zona_horaria)?;
+
+    let vencimiento = if let Ok(fecha) = DateTime::parse_from_rfc3339(valor) {
+        let fecha = fecha.with_timezone(&Utc);
+        Vencimiento {
+            fecha,
+            todo_el_dia: false,
+            dia: None,
+            zona_horaria: None,
+            limite: fecha,
+        }
+    } else if let Ok(local) = NaiveDateTime::parse_from_str(valor, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(valor, "%Y-%m-%dT%H:%M"))
+    {
+        let fecha = instante_local(tz, local)?;
+        Vencimiento {
+            fecha,
+            todo_el_dia: false,
+            dia: None,
+            zona_horaria: Some(tz.name().to_string()),
+            limite: fecha,
+        }
+    } else if let Ok(dia) = NaiveDate::parse_from_str(valor, "%Y-%m-%d") {
+        // Vence al terminar ese día en su zona
+        let siguiente = dia.succ_opt().ok_or("fecha_vencimiento fuera de rango")?;
+        Vencimiento {
+            fecha: inicio_dia(tz, dia)?,
+            todo_el_dia: true,
+            dia: Some(dia.format("%Y-%m-%d").to_string()),
+            zona_horaria: Some(tz.name().to_string()),
+            limite: inicio_dia(tz, siguiente)?,
+        }
+    } else {
+        return Err(
+            "fecha_vencimiento debe ser RFC 3339, AAAA-MM-DDTHH:MM o AAAA-MM-DD".to_string(),
+        );
+    };
+
+    if !(PRIMER_ANIO..=ULTIMO_ANIO).contains(&vencimiento.fecha.year()) {
+        return Err(format!(
+            "fecha_vencimiento debe estar entre {} y {}",
+            PRIMER_ANIO, ULTIMO_ANIO
+        ));
+    }
+    Ok(vencimiento)
+}
+
+// Tareas que vencen en [desde, hasta) según el calendario de la zona de quien consulta.
+// Las de día completo se comparan por día para que "hoy" sea el mismo día en cualquier zona
+fn entre_dias(tz: Tz, desde: NaiveDate, hasta: NaiveDate) -> Result<Document, String> {
+    let formato = |dia: NaiveDate| dia.format("%Y-%m-%d").to_string();
+    Ok(doc! {
+        "$or": [
+            {
+                "vencimiento.todo_el_dia": true,
+                "vencimiento.dia": { "$gte": formato(desde), "$lt": formato(hasta) },
+            },
+            {
+                "vencimiento.todo_el_dia": false,
+                "vencimiento.fecha": {
+                    "$gte": bson::DateTime::from_chrono(inicio_dia(tz, desde)?),
+                    "$lt": bson::DateTime::from_chrono(inicio_dia(tz, hasta)?),
+                },
+            },
+        ]
+    })
+}
+
+// Condición de MongoDB para una vista por fecha de vencimiento
+pub fn filtro_vista(vista: Vista, tz: Tz, ahora: DateTime<Utc>) -> Result<Document, String> {
+    let hoy = ahora.with_timezone(&tz).date_naive();
+    let dia = |dias: i64| hoy + Duration::days(dias);
+
+    match vista {
+        Vista::Hoy => entre_dias(tz, hoy, dia(1)),
+        Vista::Semana => {
+            // Semana de lunes a domingo
+            let lunes = dia(-(hoy.weekday().num_days_from_monday() as i64));
+            entre_dias(tz, lunes, lunes + Duration::days(7))
+        }
+        Vista::Vencidas => Ok(doc! {
+            "estado": { "$ne": bson::to_bson(&EstadoTarea::Realizada).unwrap() },
+            "vencimiento.limite": { "$lte": bson::DateTime::from_chrono(ahora) },
+        }),
+        Vista::SinFecha => Ok(doc! { "vencimiento": Bson::Null }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(valor: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(valor).unwrap().with_timezone(&Utc)
+    }
+
+    fn bson_utc(valor: &str) -> bson::DateTime {
+        bson::DateTime::from_chrono(utc(valor))
+    }
+
+    #[test]
+    fn rfc3339_es_un_instante_exacto() {
+        let v = interpretar("2024-05-10T17:00:00+02:00", Some("America/New_York")).unwrap();
+        assert_eq!(v.fecha, utc("2024-05-10T15:00:00Z"));
+        assert_eq!(v.limite, v.fecha);
+        assert!(!v.todo_el_dia);
+        assert_eq!(v.dia, None);
+        assert_eq!(v.zona_horaria, None);
+    }
+
+    #[test]
+    fn sin_desplazamiento_es_hora_local_de_la_zona() {
+        let v = interpretar("2024-05-10T17:00", Some("Europe/Madrid")).unwrap();
+        assert_eq!(v.fecha, utc("2024-05-10T15:00:00Z"));
+        assert_eq!(v.zona_horaria.as_deref(), Some("Europe/Madrid"));
+
+        let v = interpretar("2024-01-10T17:00:30", Some("Europe/Madrid")).unwrap();
+        assert_eq!(v.fecha, utc("2024-01-10T16:00:30Z"));
+
+        // Sin zona se toma UTC
+        let v = interpretar("2024-05-10T17:00", None).unwrap();
+        assert_eq!(v.fecha, utc("2024-05-10T17:00:00Z"));
+        assert_eq!(v.zona_horaria.as_deref(), Some("UTC"));
+    }
+
+    #[test]
+    fn solo_fecha_abarca_el_dia_en_la_zona() {
+        let v = interpretar("2024-05-10", Some("America/New_York")).unwrap();
+        assert!(v.todo_el_dia);
+        assert_eq!(v.dia.as_deref(), Some("2024-05-10"));
+        assert_eq!(v.fecha, utc("2024-05-10T04:00:00Z"));
+        assert_eq!(v.limite, utc("2024-05-11T04:00:00Z"));
+    }
+
+    #[test]
+    fn dia_con_cambio_de_horario() {
+        // En Madrid el 31 de marzo de 2024 solo tiene 23 horas
+        let v = interpretar("2024-03-31", Some("Europe/Madrid")).unwrap();
+        assert_eq!(v.fecha, utc("2024-03-30T23:00:00Z"));
+        assert_eq!(v.limite, utc("2024-03-31T22:00:00Z"));
+
+        // En São Paulo el 4 de noviembre de 2018 empezó a la 01:00
+        let v = interpretar("2018-11-04", Some("America/Sao_Paulo")).unwrap();
+        assert_eq!(v.fecha, utc("2018-11-04T03:00:00Z"));
+    }
+
+    #[test]
+    fn horas_inexistentes_y_repetidas() {
+        let error = interpretar("2024-03-31T02:30", Some("Europe/Madrid")).unwrap_err();
+        assert!(error.contains("no existe"), "{}", error);
+
+        // Una hora repetida se toma en su primera aparición (horario de verano)
+        let v = interpretar("2024-10-27T02:30", Some("Europe/Madrid")).unwrap();
+        assert_eq!(v.fecha, utc("2024-10-27T00:30:00Z"));
+    }
+
+    #[test]
+    fn rechaza_zonas_y_fechas_invalidas() {
+        let error = interpretar("2024-05-10", Some("Europa/Madrid")).unwrap_err();
+        assert_eq!(error, "Zona horaria desconocida: 'Europa/Madrid'");
+        assert!(zona(Some("  ")).is_ok_and(|tz| tz == Tz::UTC));
+
+        assert!(interpretar("10/05/2024", None).is_err());
+        assert!(interpretar("2024-02-30", None).is_err());
+        assert!(interpretar("1999-12-31", None).is_err());
+        assert!(interpretar("2101-01-01T00:00:00Z", None).is_err());
+    }
+
+    #[test]
+    fn vista_hoy_usa_el_dia_de_quien_consulta() {
+        // En Madrid ya es 11 de mayo aunque en UTC siga siendo día 10
+        let tz: Tz = "Europe/Madrid".parse().unwrap();
+        let filtro = filtro_vista(Vista::Hoy, tz, utc("2024-05-10T23:30:00Z")).unwrap();
+        assert_eq!(
+            filtro,
+            doc! {
+                "$or": [
+                    {
+                        "vencimiento.todo_el_dia": true,
+                        "vencimiento.dia": { "$gte": "2024-05-11", "$lt": "2024-05-12" },
+                    },
+                    {
+                        "vencimiento.todo_el_dia": false,
+                        "vencimiento.fecha": {
+                            "$gte": bson_utc("2024-05-10T22:00:00Z"),
+                            "$lt": bson_utc("2024-05-11T22:00:00Z"),
+                        },
+                    },
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn vista_semana_de_lunes_a_domingo_con_cambio_de_horario() {
+        let tz: Tz = "Europe/Madrid".parse().unwrap();
+        let filtro = filtro_vista(Vista::Semana, tz, utc("2024-03-27T10:00:00Z")).unwrap();
+        assert_eq!(
+            filtro,
+            doc! {
+                "$or": [
+                    {
+                        "vencimiento.todo_el_dia": true,
+                        "vencimiento.dia": { "$gte": "2024-03-25", "$lt": "2024-04-01" },
+                    },
+                    {
+                        "vencimiento.todo_el_dia": false,
+                        "vencimiento.fecha": {
+                            "$gte": bson_utc("2024-03-24T23:00:00Z"),
+                            "$lt": bson_utc("2024-03-31T22:00:00Z"),
+                        },
+                    },
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn vista_vencidas_compara_con_el_limite() {
+        let ahora = utc("2024-05-10T12:00:00Z");
+        let filtro = filtro_vista(Vista::Vencidas, Tz::UTC, ahora).unwrap();
+        assert_eq!(
+            filtro,
+            doc! {
+                "estado": { "$ne": "Realizada" },
+                "vencimiento.limite": { "$lte": bson_utc("2024-05-10T12:00:00Z") },
+            }
+        );
+    }
+}
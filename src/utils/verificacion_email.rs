@@ -0,0 +1,66 @@
+use std::sync::OnceLock;
+
+use crate::{
+    models::usuario::Usuario,
+    utils::{
+        entorno::variable,
+        jwt::generar_token_verificacion_email,
+        mailer::{self, Correo},
+    },
+};
+
+static POLITICA: OnceLock<PoliticaVerificacion> = OnceLock::new();
+
+// Qué se bloquea mientras el email no esté verificado (EMAIL_VERIFICATION_POLICY)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoliticaVerificacion {
+    Ninguna, // "none" (por defecto)
+    Login,   // "login": no se puede iniciar sesión
+    Tareas,  // "tareas": se puede iniciar sesión pero no crear tareas
+}
+
+// Lee la política al arrancar; un valor desconocido es un error para no desactivarla por descuido
+pub fn inicializar_desde_entorno() -> Result<(), String> {
+    let politica = match variable("EMAIL_VERIFICATION_POLICY").as_deref() {
+        None | Some("none") => PoliticaVerificacion::Ninguna,
+        Some("login") => PoliticaVerificacion::Login,
+        Some("tareas") => PoliticaVerificacion::Tareas,
+        Some(otro) => {
+            return Err(format!(
+                "EMAIL_VERIFICATION_POLICY inválido: '{}' (none, login o tareas)",
+                otro
+            ))
+        }
+    };
+
+    POLITICA
+        .set(politica)
+        .map_err(|_| "La política de verificación ya estaba inicializada".to_string())
+}
+
+pub fn politica_verificacion() -> PoliticaVerificacion {
+    *POLITICA.get().expect("Política de verificación no inicializada")
+}
+
+// Envía el enlace firmado de verificación al email del usuario
+pub async fn enviar_email_verificacion(usuario: &Usuario) -> Result<(), String> {
+    enviar_enlace_verificacion(usuario, &usuario.email).await
+}
+
+// Envía el enlace de verificación a `email`, que puede ser el email pendiente de un cambio
+pub async fn enviar_enlace_verificacion(usuario: &Usuario, email: &str) -> Result<(), String> {
+    let user_id = usuario.id.as_ref().ok_or("Usuario sin id")?.to_hex();
+    let token = generar_token_verificacion_email(&user_id, email).map_err(|e| e.to_string())?;
+
+    mailer::enviar(Correo {
+        para: email.to_string(),
+        asunto: "Verifica tu email".to_string(),
+        cuerpo: format!(
+            "Hola {},\n\nConfirma tu dirección de email abriendo el siguiente enlace \
+             (válido durante 24 horas):\n\n{}\n",
+            usuario.name,
+            mailer::enlace("/verify-email", &token)
+        ),
+    })
+    .await
+}